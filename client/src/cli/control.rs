@@ -0,0 +1,314 @@
+//! Local control interface which lets other programs (bots, dashboards, ...) use airshipper.
+//!
+//! The GUI and the terminal interface serve it while they are running, `airshipper serve`
+//! does without a user interface. Only one of them can run at a time (see [`crate::lock`]).
+//! Listens on localhost only. The address and a random token get written to the
+//! control file (see `airshipper serve --help`) which only the current user can read.
+//! A client sends a single line of JSON and receives the resulting events
+//! (see [`Event`]) as one JSON object per line until the operation finished:
+//!
+//! ```text
+//! > {"token": "...", "command": "update", "profile": "default"}
+//! < {"event":"update_found","profile":"default","installed":"...","latest":"..."}
+//! < {"event":"download_progress","profile":"default","downloaded":1024,"total":4096,"speed":512}
+//! < ...
+//! < {"event":"done"}
+//! ```
+//!
+//! Commands: `status`, `check_update`, `update`, `launch` (optionally with `server`),
+//! `cancel` and `watch` which streams all events until the connection gets closed.
+
+use super::{
+    game,
+    output::{self, Event},
+};
+use crate::{
+    filesystem, network,
+    profiles::Server,
+    state::{SavedState, UpdatePolicy},
+    Result,
+};
+use async_std::{
+    io::BufReader,
+    net::{TcpListener, TcpStream},
+    prelude::*,
+    sync::Arc,
+};
+use futures::channel::mpsc::{UnboundedReceiver, UnboundedSender};
+use serde::{Deserialize, Serialize};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Mutex,
+};
+
+/// Bytes read at most from a request
+const MAX_REQUEST_SIZE: u64 = 64 * 1024;
+
+/// Set while an operation of a client changes the saved state
+static BUSY: AtomicBool = AtomicBool::new(false);
+/// Set while the user interface of this process changes the saved state
+static FRONTEND_BUSY: AtomicBool = AtomicBool::new(false);
+
+lazy_static::lazy_static! {
+    /// User interfaces which have to know about the operations of clients
+    static ref FRONTENDS: Mutex<Vec<UnboundedSender<Notification>>> = Mutex::new(Vec::new());
+}
+
+/// Tells the user interface what clients are doing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Notification {
+    /// The saved state must not be changed until the operation finished
+    Started,
+    /// The saved state has been changed and has to be loaded again
+    Finished,
+}
+
+#[derive(Deserialize)]
+struct Request {
+    token: String,
+    #[serde(flatten)]
+    command: Command,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+enum Command {
+    Status,
+    CheckUpdate {
+        profile: Option<String>,
+    },
+    Update {
+        profile: Option<String>,
+    },
+    Launch {
+        profile: Option<String>,
+        server: Option<String>,
+    },
+    Cancel,
+    Watch,
+}
+
+/// Tells clients how to connect
+#[derive(Serialize)]
+struct ControlFile<'a> {
+    address: String,
+    token: &'a str,
+}
+
+/// Serves clients in the background while the user interface is running.
+pub fn spawn() {
+    // Until the user interface loaded the state
+    set_frontend_busy(true);
+    async_std::task::spawn(async {
+        if let Err(e) = serve(0).await {
+            log::warn!("Failed to start the control interface: {}", e);
+        }
+    });
+}
+
+/// Returns a stream of the operations of clients from now on.
+pub fn notifications() -> UnboundedReceiver<Notification> {
+    let (tx, rx) = futures::channel::mpsc::unbounded();
+    FRONTENDS.lock().unwrap().push(tx);
+    rx
+}
+
+/// Keeps clients from changing the saved state while the user interface works on it.
+pub fn set_frontend_busy(busy: bool) {
+    FRONTEND_BUSY.store(busy, Ordering::SeqCst);
+}
+
+/// Serves clients until airshipper gets closed.
+pub(super) async fn serve(port: u16) -> Result<()> {
+    let listener = TcpListener::bind(("127.0.0.1", port)).await?;
+    let token = generate_token();
+    let address = listener.local_addr()?.to_string();
+    write_control_file(&ControlFile {
+        address: address.clone(),
+        token: &token,
+    })?;
+    log::info!(
+        "Listening on {}. Address and token have been written to {}",
+        address,
+        filesystem::get_control_path().display()
+    );
+
+    let token = Arc::new(token);
+    let mut incoming = listener.incoming();
+    while let Some(stream) = incoming.next().await {
+        let stream = stream?;
+        let token = token.clone();
+        async_std::task::spawn(async move {
+            if let Err(e) = handle(stream, &token).await {
+                log::debug!("Control connection failed: {}", e);
+            }
+        });
+    }
+    Ok(())
+}
+
+async fn handle(stream: TcpStream, token: &str) -> Result<()> {
+    let mut line = String::new();
+    // Requests are short, longer ones must not make the launcher run out of memory
+    BufReader::new(&stream)
+        .take(MAX_REQUEST_SIZE)
+        .read_line(&mut line)
+        .await?;
+    let stream = &stream;
+
+    let request: Request = match serde_json::from_str(&line) {
+        Ok(request) => request,
+        Err(e) => return reply_error(stream, format!("Invalid request: {}", e)).await,
+    };
+    if request.token != token {
+        return reply_error(stream, "Invalid token".into()).await;
+    }
+    log::debug!("Control request: {:?}", request.command);
+
+    let mut events = output::subscribe();
+    if let Command::Watch = request.command {
+        while let Some(event) = events.next().await {
+            write_line(stream, &event).await?;
+        }
+        return Ok(());
+    }
+
+    // Forward the events while the operation is running
+    let mut connected = true;
+    let result = {
+        let operation = execute(request.command);
+        let forward = async {
+            while let Some(event) = events.next().await {
+                // Let the operation finish even if the client went away
+                connected = connected && write_line(stream, &event).await.is_ok();
+            }
+        };
+        futures::pin_mut!(operation, forward);
+        match futures::future::select(operation, forward).await {
+            futures::future::Either::Left((result, _)) => result,
+            futures::future::Either::Right(_) => unreachable!("event stream never ends"),
+        }
+    };
+    // Events which haven't been forwarded yet
+    while let Ok(Some(event)) = events.try_next() {
+        write_line(stream, &event).await?;
+    }
+
+    match result {
+        Ok(()) => write_line(stream, &output::to_json(&Event::Done)).await,
+        Err(e) => {
+            log::error!("{}", e);
+            write_line(
+                stream,
+                &output::to_json(&Event::Error {
+                    code: e.exit_code(),
+                    message: e.to_string(),
+                }),
+            )
+            .await
+        }
+    }
+}
+
+/// Runs the same code as the equivalent command line commands.
+/// The state gets loaded for every command as the user interface might have changed it.
+async fn execute(command: Command) -> Result<()> {
+    match command {
+        Command::Status => game::status(&SavedState::load().await?).await?,
+        Command::CheckUpdate { profile } => {
+            game::check_update(&mut SavedState::load().await?, profile.as_deref()).await?;
+        }
+        Command::Update { profile } => {
+            let _operation = Operation::start()?;
+            let mut state = SavedState::load().await?;
+            game::update(&mut state, profile.as_deref(), UpdatePolicy::Always).await?;
+            state.save().await?;
+        }
+        Command::Launch { profile, server } => {
+            let server = match server {
+                Some(address) => Some(address.parse::<Server>()?),
+                None => None,
+            };
+            let operation = Operation::start()?;
+            let mut state = SavedState::load().await?;
+            let profile = game::prepare_start(&mut state, profile.as_deref(), server.as_ref())?;
+            state.save().await?;
+            // Don't block other operations while playing
+            drop(operation);
+            async_std::task::spawn_blocking(move || profile.start(server.as_ref())).await?;
+        }
+        Command::Cancel => network::cancel_download(),
+        Command::Watch => unreachable!(),
+    }
+    Ok(())
+}
+
+/// Operation of a client which changes the saved state, only one can run at a time.
+struct Operation;
+
+impl Operation {
+    fn start() -> Result<Self> {
+        if FRONTEND_BUSY.load(Ordering::SeqCst)
+            || BUSY
+                .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+                .is_err()
+        {
+            return Err("Busy with another operation. Use `watch` to follow it.".into());
+        }
+        notify(Notification::Started);
+        Ok(Self)
+    }
+}
+
+impl Drop for Operation {
+    fn drop(&mut self) {
+        BUSY.store(false, Ordering::SeqCst);
+        notify(Notification::Finished);
+    }
+}
+
+fn notify(notification: Notification) {
+    // Drop user interfaces which went away
+    FRONTENDS
+        .lock()
+        .unwrap()
+        .retain(|x| x.unbounded_send(notification).is_ok());
+}
+
+async fn write_line(mut stream: &TcpStream, line: &str) -> Result<()> {
+    stream.write_all(line.as_bytes()).await?;
+    stream.write_all(b"\n").await?;
+    Ok(())
+}
+
+async fn reply_error(stream: &TcpStream, message: String) -> Result<()> {
+    log::debug!("Rejected control request: {}", message);
+    write_line(stream, &output::to_json(&Event::Error { code: 2, message })).await
+}
+
+fn write_control_file(contents: &ControlFile) -> Result<()> {
+    let path = filesystem::get_control_path();
+    // Permissions only get applied when creating the file
+    filesystem::remove(&path)?;
+    let json = serde_json::to_string_pretty(contents).map_err(|e| e.to_string())?;
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        // Only the user is allowed to control airshipper
+        options.mode(0o600);
+    }
+    std::io::Write::write_all(&mut options.open(&path)?, json.as_bytes())?;
+    Ok(())
+}
+
+/// Creates a token from the random number generator of the operating system.
+fn generate_token() -> String {
+    use rand::RngCore;
+
+    let mut bytes = [0; 32];
+    rand::rngs::OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|x| format!("{:02x}", x)).collect()
+}
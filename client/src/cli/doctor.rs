@@ -0,0 +1,176 @@
+//! Diagnoses the problems we keep running into and reports them in a form
+//! which can be pasted into a bug report
+
+use super::output::{self, Check, CheckStatus, Event};
+use crate::{
+    filesystem, network,
+    profiles::{Channel, Profile},
+    state::SavedState,
+    Result,
+};
+use std::path::Path;
+
+/// Warn if less space than this is left (bytes)
+const LOW_DISK_SPACE: u64 = 2 * 1024 * 1024 * 1024;
+
+/// Runs all checks and prints the report.
+/// Takes the state as result to be able to diagnose broken settings too.
+pub(super) async fn doctor(state: Result<SavedState>) -> Result<()> {
+    let mut checks = Vec::new();
+
+    checks.push(match &state {
+        Ok(state) => match &state.notice {
+            Some(notice) => Check::warning("settings", notice),
+            None => Check::ok("settings", filesystem::get_settings_path().display()),
+        },
+        Err(e) => Check::failed("settings", e),
+    });
+
+    checks.push(match filesystem::find_assets() {
+        Ok(path) => Check::ok("assets", path.display()),
+        Err(e) => Check::failed("assets", e),
+    });
+
+    checks.push(match network::proxy(network::DOWNLOAD_SERVER) {
+        Some(proxy) => match network::validate_proxy(&proxy) {
+            Ok(_) => Check::ok("proxy", proxy),
+            Err(e) => Check::failed("proxy", e),
+        },
+        None => Check::ok("proxy", "none"),
+    });
+
+    for channel in Channel::ALL {
+        let name = format!("download server ({})", channel);
+        checks.push(match network::get_version(*channel).await {
+            Ok(version) => Check::ok(name, format!("latest version {}", version)),
+            Err(e) => Check::failed(name, e),
+        });
+    }
+
+    for (name, path) in &[
+        ("disk space (profiles)", filesystem::get_data_path()),
+        ("disk space (downloads)", filesystem::get_downloads_path()),
+    ] {
+        checks.push(disk_space(name, path));
+    }
+
+    if let Ok(state) = &state {
+        for profile in &state.settings.profiles {
+            checks.push(write_access(profile));
+            checks.push(executables(profile));
+            #[cfg(target_os = "linux")]
+            checks.push(shared_libraries(profile));
+        }
+    }
+
+    let failed = checks
+        .iter()
+        .filter(|x| x.status == CheckStatus::Failed)
+        .count();
+    let total = checks.len();
+
+    output::emit(Event::Doctor {
+        version: env!("CARGO_PKG_VERSION").into(),
+        os: std::env::consts::OS.into(),
+        arch: std::env::consts::ARCH.into(),
+        portable: filesystem::is_portable(),
+        data_path: filesystem::data_path().to_string(),
+        config_path: filesystem::config_path().to_string(),
+        cache_path: filesystem::get_cache_path().display().to_string(),
+        log_path: filesystem::get_log_path().display().to_string(),
+        checks,
+    });
+
+    if failed > 0 {
+        return Err(format!("{} of {} checks failed.", failed, total).into());
+    }
+    Ok(())
+}
+
+fn disk_space(name: &str, path: &Path) -> Check {
+    use indicatif::HumanBytes;
+
+    match filesystem::free_space(path) {
+        Ok(free) if free < LOW_DISK_SPACE => Check::warning(
+            name,
+            format!("only {} free at {}", HumanBytes(free), path.display()),
+        ),
+        Ok(free) => Check::ok(
+            name,
+            format!("{} free at {}", HumanBytes(free), path.display()),
+        ),
+        Err(e) => Check::failed(name, format!("{}: {}", path.display(), e)),
+    }
+}
+
+fn write_access(profile: &Profile) -> Check {
+    let name = format!("{}: write access", profile.name);
+    let file = profile.directory.join(".airshipper-doctor");
+
+    match std::fs::write(&file, b"").and_then(|_| std::fs::remove_file(&file)) {
+        Ok(_) => Check::ok(name, profile.directory.display()),
+        Err(e) => Check::failed(name, format!("{}: {}", profile.directory.display(), e)),
+    }
+}
+
+fn executables(profile: &Profile) -> Check {
+    let name = format!("{}: executables", profile.name);
+    if profile.version.is_empty() {
+        return Check::warning(name, "not installed");
+    }
+
+    let mut problems = Vec::new();
+    for path in profile.executables() {
+        match std::fs::metadata(&path) {
+            Ok(meta) if !is_executable(&meta) => {
+                problems.push(format!("{} is not executable", path.display()))
+            }
+            Ok(_) => {}
+            Err(e) => problems.push(format!("{}: {}", path.display(), e)),
+        }
+    }
+
+    if problems.is_empty() {
+        Check::ok(name, format!("version {}", profile.version))
+    } else {
+        Check::failed(name, problems.join(", "))
+    }
+}
+
+#[cfg(unix)]
+fn is_executable(meta: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    meta.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(windows)]
+fn is_executable(_: &std::fs::Metadata) -> bool {
+    true
+}
+
+/// Uses `ldd` to find libraries voxygen needs but which are not installed.
+#[cfg(target_os = "linux")]
+fn shared_libraries(profile: &Profile) -> Check {
+    let name = format!("{}: shared libraries", profile.name);
+    let voxygen = profile.directory.join(filesystem::VOXYGEN_FILE);
+    if !voxygen.exists() {
+        return Check::warning(name, "not installed");
+    }
+
+    match std::process::Command::new("ldd").arg(&voxygen).output() {
+        Ok(out) => {
+            let missing = String::from_utf8_lossy(&out.stdout)
+                .lines()
+                .filter(|x| x.contains("not found"))
+                .filter_map(|x| x.split_whitespace().next())
+                .map(|x| x.to_owned())
+                .collect::<Vec<_>>();
+            if missing.is_empty() {
+                Check::ok(name, "all found")
+            } else {
+                Check::failed(name, format!("missing {}", missing.join(", ")))
+            }
+        }
+        Err(e) => Check::warning(name, format!("could not run ldd: {}", e)),
+    }
+}
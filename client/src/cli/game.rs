@@ -0,0 +1,179 @@
+//! Commands which install, update and start the game
+
+use super::{
+    confirm_update,
+    output::{self, Event, ProfileInfo},
+    print_install_progress, print_progress,
+};
+use crate::{
+    network,
+    profiles::{Channel, Profile, Server},
+    state::{DaemonStatus, SavedState, UpdatePolicy},
+    Result,
+};
+use std::collections::BTreeMap;
+
+/// Installs the latest version of the profile
+pub(super) async fn install(
+    state: &mut SavedState,
+    profile: Option<&str>,
+    force: bool,
+) -> Result<()> {
+    let profile = state.settings.select_profile_mut(profile)?;
+    if profile.is_installed() && !force {
+        output::emit(Event::AlreadyInstalled {
+            profile: profile.name.clone(),
+            version: profile.version.clone(),
+        });
+        return Ok(());
+    }
+
+    // Makes sure the latest version gets installed even if it's the current one.
+    profile.version.clear();
+    download_and_install(profile).await
+}
+
+/// Checks whether a newer version of the profile is available
+pub(super) async fn check_update(state: &mut SavedState, profile: Option<&str>) -> Result<bool> {
+    let profile = state.settings.select_profile_mut(profile)?;
+    let latest = profile.check_for_update().await?;
+    if latest != profile.version {
+        output::emit(Event::UpdateFound {
+            profile: profile.name.clone(),
+            installed: profile.version.clone(),
+            latest,
+        });
+        Ok(true)
+    } else {
+        output::emit(Event::UpToDate {
+            profile: profile.name.clone(),
+            version: latest,
+        });
+        Ok(false)
+    }
+}
+
+/// Updates the profile if a newer version is available and the policy allows it
+pub(super) async fn update(
+    state: &mut SavedState,
+    profile: Option<&str>,
+    policy: UpdatePolicy,
+) -> Result<()> {
+    if check_update(state, profile).await? && confirm_update(policy, "Do you want to update?")? {
+        download_and_install(state.settings.select_profile_mut(profile)?).await?;
+    }
+    Ok(())
+}
+
+/// Starts the game without updating it
+pub(super) fn start(
+    state: &mut SavedState,
+    profile: Option<&str>,
+    server: Option<&Server>,
+) -> Result<()> {
+    prepare_start(state, profile, server)?.start(server)
+}
+
+/// Makes sure the profile can be started and returns it.
+/// The game has to be started by the caller.
+pub(super) fn prepare_start(
+    state: &mut SavedState,
+    profile: Option<&str>,
+    server: Option<&Server>,
+) -> Result<Profile> {
+    let profile = state.settings.select_profile_mut(profile)?;
+    if !profile.is_installed() {
+        return Err(format!(
+            "'{}' is not installed yet. Use `airshipper install` first.",
+            profile.name
+        )
+        .into());
+    }
+
+    output::emit(Event::Launching {
+        profile: profile.name.clone(),
+        server: server.map(|x| x.to_string()),
+    });
+    profile.record_launch();
+    Ok(profile.clone())
+}
+
+/// Updates (according to the update policy) and starts the game
+pub(super) async fn run(
+    state: &mut SavedState,
+    profile: Option<&str>,
+    server: Option<&Server>,
+) -> Result<()> {
+    let policy = state.settings.game_updates;
+    update(state, profile, policy).await?;
+    start(state, profile, server)
+}
+
+/// Prints the latest version of every channel
+pub(super) async fn versions() -> Result<()> {
+    let mut channels = BTreeMap::new();
+    for channel in Channel::ALL {
+        channels.insert(channel.to_string(), network::get_version(*channel).await?);
+    }
+    output::emit(Event::Versions { channels });
+    Ok(())
+}
+
+/// Prints installed and latest version of every profile
+pub(super) async fn status(state: &SavedState) -> Result<()> {
+    let mut profiles = Vec::new();
+    for profile in &state.settings.profiles {
+        let latest = match profile.check_for_update().await {
+            Ok(latest) => Some(latest),
+            Err(e) => {
+                log::debug!(
+                    "Failed to fetch latest version of '{}': {}",
+                    profile.name,
+                    e
+                );
+                None
+            }
+        };
+        let active = profile.name == state.settings.active_profile;
+        profiles.push(ProfileInfo::new(profile, active, latest));
+    }
+    output::emit(Event::Status {
+        profiles,
+        daemon: DaemonStatus::load().await,
+    });
+    Ok(())
+}
+
+async fn download_and_install(profile: &mut Profile) -> Result<()> {
+    // The daemon might have downloaded it already
+    let staged = profile.staged_version();
+    if staged.is_some() && staged == Some(profile.check_for_update().await?) {
+        log::info!("Using the already downloaded version.");
+    } else {
+        output::emit(Event::DownloadStarted {
+            profile: profile.name.clone(),
+        });
+        let progress = profile.start_download().await?;
+        print_progress(&profile.name, &progress).await;
+        if let Some(Err(e)) = progress.finished() {
+            return Err(e);
+        }
+    }
+    output::emit(Event::Installing {
+        profile: profile.name.clone(),
+    });
+    let progress = network::InstallProgress::default();
+    let install = profile.clone().install_with_progress(progress.clone());
+    let printing = print_install_progress(&profile.name, &progress);
+    futures::pin_mut!(install, printing);
+    *profile = match futures::future::select(install, printing).await {
+        futures::future::Either::Left((result, _)) => result?,
+        // Stops printing once all files are extracted
+        futures::future::Either::Right(((), install)) => install.await?,
+    };
+    output::emit(Event::Installed {
+        profile: profile.name.clone(),
+        version: profile.version.clone(),
+    });
+    Ok(())
+}
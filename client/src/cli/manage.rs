@@ -0,0 +1,202 @@
+//! Commands which manage profiles, settings and the files of airshipper
+
+use super::{
+    output::{self, Event, ProfileInfo},
+    usage,
+};
+use crate::{
+    filesystem, network,
+    profiles::{Channel, Profile, Server},
+    state::{Cache, SavedState},
+    store, Result,
+};
+
+/// Lists, adds or removes favourite servers of a profile
+pub(super) fn favourites(
+    state: &mut SavedState,
+    profile: Option<&str>,
+    m: &clap::ArgMatches,
+) -> Result<()> {
+    let profile = state.settings.select_profile_mut(profile)?;
+    match m.subcommand() {
+        ("add", Some(m)) => {
+            let server: Server = m.value_of("server").unwrap_or_default().parse()?;
+            output::emit(Event::FavouriteAdded {
+                profile: profile.name.clone(),
+                server: server.to_string(),
+                added: profile.add_favourite(server),
+            });
+        }
+        ("remove", Some(m)) => {
+            let server: Server = m.value_of("server").unwrap_or_default().parse()?;
+            output::emit(Event::FavouriteRemoved {
+                profile: profile.name.clone(),
+                server: server.to_string(),
+                removed: profile.remove_favourite(&server),
+            });
+        }
+        _ => output::emit(Event::Favourites {
+            profile: profile.name.clone(),
+            servers: profile
+                .favourite_servers
+                .iter()
+                .map(|x| x.to_string())
+                .collect(),
+        }),
+    }
+    Ok(())
+}
+
+/// Lists, creates, removes or activates profiles
+pub(super) fn profile(state: &mut SavedState, m: &clap::ArgMatches) -> Result<()> {
+    let settings = &mut state.settings;
+    match m.subcommand() {
+        ("create", Some(m)) => {
+            let name = m.value_of("name").unwrap_or_default();
+            if !Profile::is_valid_name(name) {
+                return Err(format!(
+                    "'{}' is not a valid profile name. Only letters, digits, '-', '_' and '.' are allowed.",
+                    name
+                )
+                .into());
+            }
+            let channel: Channel = m.value_of("channel").unwrap_or_default().parse()?;
+            settings.add_profile(Profile::new(name.to_owned(), channel))?;
+            output::emit(Event::ProfileCreated {
+                profile: name.to_owned(),
+                channel: channel.to_string(),
+            });
+        }
+        ("remove", Some(m)) => {
+            let profile = settings.remove_profile(m.value_of("name").unwrap_or_default())?;
+            let delete_files = !m.is_present("keep_files");
+            let mut freed = 0;
+            if delete_files {
+                // Refuse to delete the files while the game is running or being installed
+                let lock = if profile.directory.exists() {
+                    Some(profile.lock()?)
+                } else {
+                    None
+                };
+                if filesystem::get_downloads_path()
+                    .join(&profile.name)
+                    .exists()
+                {
+                    freed += network::remove_download(&profile.name)?;
+                }
+                drop(lock);
+                if profile.directory.exists() {
+                    std::fs::remove_dir_all(&profile.directory)?;
+                }
+                store::forget(&profile.name)?;
+                freed += store::collect_garbage()?;
+            }
+            output::emit(Event::ProfileRemoved {
+                profile: profile.name,
+                files_deleted: delete_files,
+                freed,
+            });
+        }
+        ("use", Some(m)) => {
+            let name = m.value_of("name").unwrap_or_default();
+            settings.set_active_profile(name)?;
+            output::emit(Event::ProfileActivated {
+                profile: name.to_owned(),
+            });
+        }
+        _ => output::emit(Event::Profiles {
+            profiles: settings
+                .profiles
+                .iter()
+                .map(|x| ProfileInfo::new(x, x.name == settings.active_profile, None))
+                .collect(),
+        }),
+    }
+    Ok(())
+}
+
+/// Shows or changes settings
+pub(super) fn config(state: &mut SavedState, m: &clap::ArgMatches) -> Result<()> {
+    let settings = &mut state.settings;
+    let values = match m.subcommand() {
+        ("set", Some(m)) => {
+            let key = m.value_of("key").unwrap_or_default();
+            settings.set(key, m.value_of("value").unwrap_or_default())?;
+            vec![(key.to_owned(), settings.get(key)?)]
+        }
+        ("get", Some(m)) if m.is_present("key") => {
+            let key = m.value_of("key").unwrap_or_default();
+            vec![(key.to_owned(), settings.get(key)?)]
+        }
+        _ => settings.values(),
+    };
+    output::emit(Event::Config {
+        values: values.into_iter().collect(),
+    });
+    Ok(())
+}
+
+/// Prints the latest lines of the log file
+pub(super) fn logs(m: &clap::ArgMatches) -> Result<()> {
+    let path = filesystem::get_log_path();
+    if m.is_present("path") {
+        output::emit(Event::LogFile {
+            path: path.display().to_string(),
+        });
+        return Ok(());
+    }
+
+    let lines = clap::value_t!(m, "lines", usize).map_err(usage)?;
+    let contents = std::fs::read(&path)?;
+    let contents = String::from_utf8_lossy(&contents);
+    let contents = contents.lines().collect::<Vec<_>>();
+    output::emit(Event::Logs {
+        path: path.display().to_string(),
+        lines: contents[contents.len().saturating_sub(lines)..]
+            .iter()
+            .map(|x| x.to_string())
+            .collect(),
+    });
+    Ok(())
+}
+
+/// Removes cached data, downloads and stored files no profile uses anymore.
+/// Skips the downloads of profiles which are busy.
+pub(super) fn clean(state: &mut SavedState) -> Result<()> {
+    let mut freed = store::collect_garbage()?;
+    let mut skipped = Vec::new();
+    let downloads = std::fs::read_dir(filesystem::get_downloads_path())
+        .into_iter()
+        .flatten()
+        .filter_map(|x| x.ok())
+        .filter(|x| x.path().is_dir());
+    for entry in downloads {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        // Directories of removed profiles are only protected by the download lock
+        let profile = state.settings.profiles.iter().find(|x| x.name == name);
+        let result = match profile.filter(|x| x.directory.exists()) {
+            Some(profile) => profile
+                .lock()
+                .and_then(|_lock| network::remove_download(&name)),
+            None => network::remove_download(&name),
+        };
+        match result {
+            Ok(bytes) => freed += bytes,
+            Err(e) => {
+                log::warn!("Kept the downloads of '{}': {}", name, e);
+                skipped.push(name);
+            }
+        }
+    }
+    for path in &[
+        filesystem::get_cache_file_path(),
+        filesystem::get_http_cache_path(),
+    ] {
+        freed += filesystem::size(path);
+        filesystem::remove(path)?;
+    }
+    state.cache = Cache::default();
+
+    output::emit(Event::Cleaned { freed, skipped });
+    Ok(())
+}
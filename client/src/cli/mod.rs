@@ -1,14 +1,60 @@
+pub mod control;
+mod doctor;
+mod game;
+mod manage;
+mod news;
+pub mod output;
+
 #[cfg(feature = "gui")]
 use crate::gui;
-use crate::{filesystem, logger, state::SavedState, Result};
+use crate::{
+    daemon,
+    error::ClientError,
+    filesystem,
+    lock::Lock,
+    logger, network,
+    profiles::Server,
+    state::{SavedState, UpdatePolicy},
+    Result,
+};
 use clap::{load_yaml, App};
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+/// Whether the user can be asked questions
+static INTERACTIVE: AtomicBool = AtomicBool::new(false);
 
 /// Process command line arguments and optionally starts GUI
 pub async fn process() -> Result<()> {
     let yml = load_yaml!("clap.yml");
     let version = format!("v{}", env!("CARGO_PKG_VERSION"));
     let app = App::from_yaml(yml).version(&*version);
-    let m = app.clone().get_matches();
+    #[cfg(feature = "tui")]
+    let app = app.subcommand(
+        clap::SubCommand::with_name("tui")
+            .about("starts the full screen terminal interface instead of the GUI"),
+    );
+    let m = match app.get_matches_safe() {
+        Ok(m) => m,
+        Err(e) => match e.kind {
+            clap::ErrorKind::HelpDisplayed | clap::ErrorKind::VersionDisplayed => e.exit(),
+            _ => return Err(usage(e)),
+        },
+    };
+
+    output::set_json(m.value_of("output") == Some("json"));
+    // Nobody would answer (e.g. cron jobs or containers)
+    INTERACTIVE.store(
+        !m.is_present("yes") && atty::is(atty::Stream::Stdin),
+        Ordering::Relaxed,
+    );
+
+    // Has to happen before anything accesses the filesystem
+    if let Some(root) = m.value_of_os("root") {
+        std::env::set_var(filesystem::ROOT_ENV, root);
+    }
 
     let level = match m.occurrences_of("log") {
         0 => log::LevelFilter::Info,
@@ -17,22 +63,70 @@ pub async fn process() -> Result<()> {
         _ => log::LevelFilter::Trace,
     };
 
-    if let Err(e) = logger::log(level) {
+    let migrated = filesystem::migrate_layout();
+
+    if let Err(e) = logger::log(level, output::is_json()) {
         panic!("Failed to set logging: {}", e);
     }
 
     log::debug!("Running on {}", std::env::consts::OS);
-    log::debug!("Base Path: {}", filesystem::base_path());
+    for (from, to) in migrated {
+        log::debug!("Moved {} to {}", from.display(), to.display());
+    }
+    log::debug!("Data Path: {}", filesystem::data_path());
+    log::debug!("Config Path: {}", filesystem::config_path());
+    log::debug!("State Path: {}", filesystem::state_path());
+    if filesystem::is_portable() {
+        log::debug!("Running in portable mode.");
+    }
     log::debug!("Log file: {}", filesystem::get_log_path().display());
     log::debug!("Assets Path: {}", filesystem::assets_path());
-    #[cfg(windows)]
     log::debug!("Cache Path: {}", filesystem::get_cache_path().display());
 
+    // The daemon must not keep the launcher from being used
+    if let ("daemon", Some(m)) = m.subcommand() {
+        let interval = clap::value_t!(m, "interval", u64).map_err(usage)?;
+        let max_rate = match m.value_of("max_rate") {
+            Some(_) => Some(clap::value_t!(m, "max_rate", u64).map_err(usage)? * 1024),
+            None => None,
+        };
+        return daemon::run(Duration::from_secs(interval.max(1) * 60), max_rate).await;
+    }
+
+    // Only one launcher may manage the profiles at a time, looking at them works alongside
+    let read_only = is_read_only(&m);
+    let _lock = if read_only {
+        None
+    } else {
+        match Lock::acquire(filesystem::get_lock_path())? {
+            Some(lock) => Some(lock),
+            None => return Err("Airshipper is already running!".into()),
+        }
+    };
+
+    let state = SavedState::load().await;
+    // Has to work with broken settings as well
+    if m.subcommand_name() == Some("doctor") {
+        return doctor::doctor(state).await;
+    }
+    let mut state = state?;
+
+    // Saving would undo the changes of the running launcher
+    if read_only {
+        return process_arguments(&mut state, m).await;
+    }
+
     // Check for updates (windows only)
     #[cfg(windows)]
-    crate::updater::update().await?;
+    crate::updater::update(state.settings.launcher_updates).await?;
 
-    let mut state = SavedState::load().await.unwrap_or_default();
+    if let ("serve", Some(m)) = m.subcommand() {
+        let port = match m.value_of("port") {
+            Some(_) => clap::value_t!(m, "port", u16).map_err(usage)?,
+            None => 0,
+        };
+        return control::serve(port).await;
+    }
 
     // handle arguments
     process_arguments(&mut state, m).await?;
@@ -43,92 +137,204 @@ pub async fn process() -> Result<()> {
     Ok(())
 }
 
-async fn process_arguments<'n, 'a>(
-    mut state: &mut SavedState,
-    m: clap::ArgMatches<'n>,
-) -> Result<()> {
-    if m.is_present("update") {
-        update(&mut state, true).await?;
-    } else if m.is_present("start") {
-        // TODO: Check if profile is installed...
-        log::info!("Starting...");
-        start(&mut state).await?;
-    } else if m.is_present("run") {
-        update(&mut state, false).await?;
-        start(&mut state).await?;
-    } else {
-        #[cfg(feature = "gui")]
-        gui::run();
-        #[cfg(not(feature = "gui"))]
-        {
-            update(&mut state, false).await?;
-            start(&mut state).await?;
+async fn process_arguments<'n>(state: &mut SavedState, m: clap::ArgMatches<'n>) -> Result<()> {
+    // Profile to use instead of the active one
+    let profile = m.value_of("profile_name");
+
+    match m.subcommand() {
+        ("install", Some(m)) => game::install(state, profile, m.is_present("force")).await?,
+        ("update", _) => game::update(state, profile, UpdatePolicy::Always).await?,
+        ("start", _) => game::start(state, profile, None)?,
+        ("run", _) => game::run(state, profile, None).await?,
+        ("play", Some(m)) => {
+            let server = match m.value_of("server") {
+                Some(address) => Some(address.parse::<Server>()?),
+                None => None,
+            };
+            game::run(state, profile, server.as_ref()).await?;
+        }
+        ("versions", _) => game::versions().await?,
+        ("status", _) => game::status(state).await?,
+        ("favourites", Some(m)) => manage::favourites(state, profile, m)?,
+        ("profile", Some(m)) => manage::profile(state, m)?,
+        ("config", Some(m)) => manage::config(state, m)?,
+        ("logs", Some(m)) => manage::logs(m)?,
+        ("clean", _) => manage::clean(state)?,
+        ("changelog", Some(m)) => news::changelog(state, m).await?,
+        ("news", Some(m)) => news::news(state, m).await?,
+        #[cfg(feature = "tui")]
+        ("tui", _) => {
+            if !atty::is(atty::Stream::Stdout) {
+                return Err("The terminal interface has to be started in a terminal.".into());
+            }
+            control::spawn();
+            crate::tui::run(state).await?;
+        }
+        _ => {
+            #[cfg(feature = "gui")]
+            {
+                if has_display() {
+                    control::spawn();
+                    gui::run();
+                    return Ok(());
+                }
+                log::debug!("No graphical environment found.");
+            }
+            #[cfg(feature = "tui")]
+            {
+                if is_interactive() && atty::is(atty::Stream::Stdout) {
+                    control::spawn();
+                    return crate::tui::run(state).await;
+                }
+            }
+            game::run(state, profile, None).await?;
         }
     }
     Ok(())
 }
 
-async fn update(state: &mut SavedState, do_not_ask: bool) -> Result<()> {
-    if state.check_for_profile_update().await? != state.active_profile.version {
-        if do_not_ask {
-            log::info!("Updating...");
-            let metrics = state.update_profile().await?;
-            print_progress(metrics).await;
-            log::info!("Extracting...");
-            state.install_profile().await?;
-            log::info!("Done!");
-        } else {
-            log::info!("Update found, do you want to update? [Y/n]");
-            if confirm_action()? {
-                let metrics = state.update_profile().await?;
-                print_progress(metrics).await;
-                log::info!("Extracting...");
-                state.install_profile().await?;
-                log::info!("Done!");
-            }
+/// Whether the GUI can be shown, e.g. not when connected via ssh.
+#[cfg(feature = "gui")]
+fn has_display() -> bool {
+    if cfg!(any(windows, target_os = "macos")) {
+        return true;
+    }
+    ["DISPLAY", "WAYLAND_DISPLAY"]
+        .iter()
+        .any(|x| std::env::var_os(x).map_or(false, |x| !x.is_empty()))
+}
+
+/// Whether the command only looks at the profiles and settings without changing them.
+fn is_read_only(m: &clap::ArgMatches) -> bool {
+    match m.subcommand() {
+        ("status", _) | ("versions", _) | ("logs", _) | ("doctor", _) => true,
+        // Updates of the cached changelog and news don't have to be saved
+        ("changelog", _) | ("news", _) => true,
+        ("profile", Some(m)) | ("favourites", Some(m)) => {
+            matches!(m.subcommand_name(), None | Some("list"))
         }
+        ("config", Some(m)) => m.subcommand_name() != Some("set"),
+        _ => false,
+    }
+}
+
+async fn print_progress(profile: &str, progress: &network::Progress) {
+    use indicatif::{HumanBytes, ProgressBar, ProgressStyle};
+
+    // The progress bar would mess up the JSON output or the terminal interface
+    let bar = if output::is_json() || logger::is_console_muted() {
+        ProgressBar::hidden()
     } else {
-        log::info!("Profile already up-to-date.");
+        ProgressBar::new(0).with_style(
+            ProgressStyle::default_bar()
+                .template(
+                    "[{elapsed_precise}] [{bar:40.green/white}] {bytes}/{total_bytes} ({eta}) {msg}",
+                )
+                .progress_chars("=>-"),
+        )
+    };
+
+    for tick in 0.. {
+        let (downloaded, total) = progress.download_progress();
+        if progress.finished().is_some() {
+            break;
+        }
+        bar.set_position(downloaded);
+        bar.set_length(total);
+        bar.set_message(&match progress.rate_limit() {
+            Some(limit) => format!(
+                "{}/sec (limited to {}/sec)",
+                HumanBytes(progress.download_speed() as u64),
+                HumanBytes(limit)
+            ),
+            None => format!("{}/sec", HumanBytes(progress.download_speed() as u64)),
+        });
+        // Once a second is plenty for scripts
+        if tick % 5 == 0 {
+            output::emit(output::Event::DownloadProgress {
+                profile: profile.to_owned(),
+                downloaded,
+                total,
+                speed: progress.download_speed() as u64,
+                rate_limit: progress.rate_limit(),
+            });
+        }
+
+        async_std::task::sleep(Duration::from_millis(200)).await;
     }
-    Ok(())
 }
 
-async fn print_progress(metrics: isahc::Metrics) {
-    use indicatif::{FormattedDuration, HumanBytes, ProgressBar, ProgressStyle};
+async fn print_install_progress(profile: &str, progress: &network::InstallProgress) {
+    use indicatif::{ProgressBar, ProgressStyle};
 
-    let bar = ProgressBar::new(0).with_style(
-        ProgressStyle::default_bar()
-            .template("[{elapsed_precise}] [{bar:40.green/white}] {bytes}/{total_bytes} ({eta})")
-            .progress_chars("=>-"),
-    );
+    // The progress bar would mess up the JSON output or the terminal interface
+    let bar = if output::is_json() || logger::is_console_muted() {
+        ProgressBar::hidden()
+    } else {
+        ProgressBar::new(0).with_style(
+            ProgressStyle::default_bar()
+                .template("[{elapsed_precise}] [{bar:40.green/white}] {pos}/{len} files {wide_msg}")
+                .progress_chars("=>-"),
+        )
+    };
 
-    loop {
-        let percentage =
-            ((metrics.download_progress().0 * 100) / metrics.download_progress().1) as f32;
-        if percentage >= 100.0 {
+    for tick in 0.. {
+        if progress.is_finished() {
             break;
         }
-        bar.set_position(metrics.download_progress().0);
-        bar.set_length(metrics.download_progress().1);
-        bar.set_message(&format!(
-            "time: {}  speed: {}/sec",
-            FormattedDuration(metrics.total_time()),
-            HumanBytes(metrics.download_speed() as u64),
-        ));
-
-        std::thread::sleep(std::time::Duration::from_millis(200));
+        let (files, total_files) = progress.files();
+        let file = progress.current_file();
+        bar.set_position(files);
+        bar.set_length(total_files);
+        bar.set_message(&file);
+        // Once a second is plenty for scripts
+        if tick % 5 == 0 && total_files != 0 {
+            output::emit(output::Event::InstallProgress {
+                profile: profile.to_owned(),
+                files,
+                total_files,
+                file,
+            });
+        }
+
+        async_std::task::sleep(Duration::from_millis(200)).await;
     }
 }
 
-async fn start(state: &mut SavedState) -> Result<()> {
-    log::info!("Starting...");
-    Ok(state.start_profile().await?)
+/// Turns invalid command line arguments into an error with the matching exit code.
+fn usage(error: clap::Error) -> ClientError {
+    ClientError::UsageError(error.message)
+}
+
+/// Returns false if airshipper must not wait for user input.
+pub fn is_interactive() -> bool {
+    INTERACTIVE.load(Ordering::Relaxed)
+}
+
+/// Decides whether an available update gets installed according to the policy.
+/// The user only gets asked when running interactively, otherwise the update is accepted.
+pub fn confirm_update(policy: UpdatePolicy, question: &str) -> Result<bool> {
+    match policy {
+        UpdatePolicy::Always => Ok(true),
+        UpdatePolicy::Never => {
+            log::info!("Skipping update as the update policy is 'never'.");
+            Ok(false)
+        }
+        UpdatePolicy::Ask if is_interactive() => {
+            log::info!("{} [Y/n]", question);
+            confirm_action()
+        }
+        UpdatePolicy::Ask => {
+            log::info!("{} [Y/n] y (non-interactive)", question);
+            Ok(true)
+        }
+    }
 }
 
 /// Will read from stdin for confirmation
 /// NOTE: no input = true
 /// Temporary...
-pub fn confirm_action() -> Result<bool> {
+fn confirm_action() -> Result<bool> {
     let mut buffer = String::new();
     let _ = std::io::stdin().read_line(&mut buffer)?;
     buffer = buffer.to_lowercase();
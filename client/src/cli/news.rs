@@ -0,0 +1,80 @@
+//! Commands which show the changelog and news of Veloren
+
+use super::{
+    output::{self, ChangelogSection, Event},
+    usage,
+};
+use crate::{state::SavedState, Result};
+
+/// Prints the changelog, optionally only the changes after a version
+pub(super) async fn changelog(state: &mut SavedState, m: &clap::ArgMatches<'_>) -> Result<()> {
+    refresh(
+        state.cache.update_changelog().await,
+        state.cache.changelog.is_empty(),
+    )?;
+
+    let since = m.value_of("since").map(|x| x.trim_start_matches('v'));
+    let mut sections = parse_changelog(&state.cache.changelog);
+    if let Some(since) = since {
+        match sections.iter().position(|x| x.version == since) {
+            Some(idx) => sections.truncate(idx),
+            None => return Err(format!("Version '{}' is not part of the changelog.", since).into()),
+        }
+    }
+
+    sections.retain(|x| !x.changes.is_empty());
+
+    output::emit(Event::Changelog { sections });
+    Ok(())
+}
+
+/// Prints the latest news
+pub(super) async fn news(state: &mut SavedState, m: &clap::ArgMatches<'_>) -> Result<()> {
+    refresh(state.cache.update_news().await, state.cache.news.is_empty())?;
+
+    let limit = clap::value_t!(m, "limit", usize).map_err(usage)?;
+    output::emit(Event::News {
+        posts: state.cache.news.iter().take(limit).cloned().collect(),
+    });
+    Ok(())
+}
+
+/// Falls back to the cached data if it could not be updated.
+fn refresh(result: Result<bool>, cache_empty: bool) -> Result<()> {
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) if !cache_empty => {
+            log::warn!("Showing cached data as fetching failed: {}", e);
+            Ok(())
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Splits the changelog into the changes of each version, starting with the newest.
+fn parse_changelog(changelog: &str) -> Vec<ChangelogSection> {
+    // The unreleased header gets stripped when querying the changelog
+    let mut sections = vec![ChangelogSection {
+        version: "Unreleased".into(),
+        changes: String::new(),
+    }];
+
+    for line in changelog.lines() {
+        if line.starts_with("## [") {
+            // e.g. ## [0.5.0] - 2020-01-31
+            let version = line[4..].split(']').next().unwrap_or_default();
+            sections.push(ChangelogSection {
+                version: version.into(),
+                changes: String::new(),
+            });
+        } else if let Some(section) = sections.last_mut() {
+            section.changes.push_str(line);
+            section.changes.push('\n');
+        }
+    }
+
+    for section in &mut sections {
+        section.changes = section.changes.trim().to_owned();
+    }
+    sections
+}
@@ -0,0 +1,541 @@
+//! Results of commands which get printed either human readable or as JSON.
+//!
+//! In JSON mode every event is printed as a single line JSON object on stdout
+//! while the log gets redirected to stderr, which makes it easy to consume by scripts.
+
+use crate::{
+    error::ClientError,
+    filesystem, logger,
+    network::Post,
+    profiles::{DiskUsage, Profile},
+    state::{Activity, DaemonStatus},
+};
+use chrono::{DateTime, Local, TimeZone};
+use derive_more::Display;
+use futures::channel::mpsc::{UnboundedReceiver, UnboundedSender};
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+static JSON: AtomicBool = AtomicBool::new(false);
+
+lazy_static::lazy_static! {
+    /// Receive every event as JSON, e.g. clients of the control interface
+    static ref SUBSCRIBERS: Mutex<Vec<UnboundedSender<String>>> = Mutex::new(Vec::new());
+}
+
+/// Switches between human readable and JSON output.
+pub fn set_json(enabled: bool) {
+    JSON.store(enabled, Ordering::Relaxed);
+}
+
+pub fn is_json() -> bool {
+    JSON.load(Ordering::Relaxed)
+}
+
+/// Returns a stream of all events emitted from now on, serialized as JSON.
+pub fn subscribe() -> UnboundedReceiver<String> {
+    let (tx, rx) = futures::channel::mpsc::unbounded();
+    SUBSCRIBERS.lock().unwrap().push(tx);
+    rx
+}
+
+/// Serializes the event as single line of JSON.
+pub fn to_json(event: &Event) -> String {
+    serde_json::to_string(event).unwrap_or_else(|e| {
+        log::error!("Failed to serialize {:?}: {}", event, e);
+        String::new()
+    })
+}
+
+fn publish(event: &Event) {
+    let mut subscribers = SUBSCRIBERS.lock().unwrap();
+    if subscribers.is_empty() {
+        return;
+    }
+    let json = to_json(event);
+    // Drop subscribers which went away
+    subscribers.retain(|x| x.unbounded_send(json.clone()).is_ok());
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    UpdateFound {
+        profile: String,
+        installed: String,
+        latest: String,
+    },
+    UpToDate {
+        profile: String,
+        version: String,
+    },
+    AlreadyInstalled {
+        profile: String,
+        version: String,
+    },
+    DownloadStarted {
+        profile: String,
+    },
+    DownloadProgress {
+        profile: String,
+        /// Bytes
+        downloaded: u64,
+        /// Bytes
+        total: u64,
+        /// Bytes per second
+        speed: u64,
+        /// Bytes per second, `None` if unlimited
+        rate_limit: Option<u64>,
+    },
+    Installing {
+        profile: String,
+    },
+    InstallProgress {
+        profile: String,
+        /// Extracted files
+        files: u64,
+        total_files: u64,
+        /// File extracted last
+        file: String,
+    },
+    Installed {
+        profile: String,
+        version: String,
+    },
+    Launching {
+        profile: String,
+        server: Option<String>,
+    },
+    Versions {
+        channels: BTreeMap<String, String>,
+    },
+    Profiles {
+        profiles: Vec<ProfileInfo>,
+    },
+    Status {
+        profiles: Vec<ProfileInfo>,
+        /// `None` if the daemon is not running
+        daemon: Option<DaemonStatus>,
+    },
+    ProfileCreated {
+        profile: String,
+        channel: String,
+    },
+    ProfileRemoved {
+        profile: String,
+        files_deleted: bool,
+        /// Bytes of stored files no other profile uses
+        freed: u64,
+    },
+    ProfileActivated {
+        profile: String,
+    },
+    Favourites {
+        profile: String,
+        servers: Vec<String>,
+    },
+    FavouriteAdded {
+        profile: String,
+        server: String,
+        /// False if it was a favourite already
+        added: bool,
+    },
+    FavouriteRemoved {
+        profile: String,
+        server: String,
+        /// False if it wasn't a favourite
+        removed: bool,
+    },
+    Config {
+        values: BTreeMap<String, String>,
+    },
+    LogFile {
+        path: String,
+    },
+    Logs {
+        path: String,
+        lines: Vec<String>,
+    },
+    Cleaned {
+        /// Bytes
+        freed: u64,
+        /// Profiles whose downloads were kept as they are busy
+        skipped: Vec<String>,
+    },
+    Changelog {
+        /// Newest first
+        sections: Vec<ChangelogSection>,
+    },
+    News {
+        posts: Vec<Post>,
+    },
+    Doctor {
+        version: String,
+        os: String,
+        arch: String,
+        portable: bool,
+        data_path: String,
+        config_path: String,
+        cache_path: String,
+        log_path: String,
+        checks: Vec<Check>,
+    },
+    /// The requested operation finished
+    Done,
+    Error {
+        /// Same as the exit code
+        code: i32,
+        message: String,
+    },
+}
+
+/// Summary of a profile as used by `profile list` and `status`.
+#[derive(Debug, Serialize)]
+pub struct ProfileInfo {
+    pub name: String,
+    pub channel: String,
+    pub active: bool,
+    /// Version which is installed, `None` if it's not installed yet
+    pub installed: Option<String>,
+    /// Latest remote version, `None` if unknown
+    pub latest: Option<String>,
+    pub directory: String,
+    /// Bytes used by the profile directory
+    pub disk_usage: u64,
+    /// What the space is used for
+    pub disk_breakdown: DiskUsage,
+    /// `None` if it has never been started
+    pub last_launch: Option<DateTime<Local>>,
+}
+
+impl ProfileInfo {
+    pub fn new(profile: &Profile, active: bool, latest: Option<String>) -> Self {
+        Self {
+            name: profile.name.clone(),
+            channel: profile.channel.to_string(),
+            active,
+            installed: if profile.is_installed() {
+                Some(profile.version.clone())
+            } else {
+                None
+            },
+            latest,
+            directory: profile.directory.display().to_string(),
+            disk_usage: filesystem::size(&profile.directory),
+            disk_breakdown: profile.disk_usage(),
+            last_launch: profile.last_launch.map(|x| Local.timestamp(x, 0)),
+        }
+    }
+
+    fn marker(&self) -> &str {
+        if self.active {
+            "*"
+        } else {
+            " "
+        }
+    }
+
+    fn installed(&self) -> &str {
+        self.installed.as_deref().unwrap_or("not installed")
+    }
+}
+
+/// Changes of a single version.
+#[derive(Debug, Serialize)]
+pub struct ChangelogSection {
+    pub version: String,
+    /// Markdown
+    pub changes: String,
+}
+
+/// Result of a single check done by `doctor`.
+#[derive(Debug, Serialize)]
+pub struct Check {
+    pub name: String,
+    pub status: CheckStatus,
+    pub details: String,
+}
+
+#[derive(Debug, Display, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    #[display(fmt = " OK ")]
+    Ok,
+    #[display(fmt = "WARN")]
+    Warning,
+    #[display(fmt = "FAIL")]
+    Failed,
+}
+
+impl Check {
+    pub fn ok(name: impl ToString, details: impl ToString) -> Self {
+        Self::new(name, CheckStatus::Ok, details)
+    }
+
+    pub fn warning(name: impl ToString, details: impl ToString) -> Self {
+        Self::new(name, CheckStatus::Warning, details)
+    }
+
+    pub fn failed(name: impl ToString, details: impl ToString) -> Self {
+        Self::new(name, CheckStatus::Failed, details)
+    }
+
+    fn new(name: impl ToString, status: CheckStatus, details: impl ToString) -> Self {
+        Self {
+            name: name.to_string(),
+            status,
+            details: details.to_string(),
+        }
+    }
+}
+
+/// e.g. `1.20 GB (build 1.10 GB, 900 MB shared - data 100 MB - downloaded 0 B)`
+pub fn disk_usage_text(usage: &DiskUsage) -> String {
+    use indicatif::HumanBytes;
+
+    format!(
+        "{} (build {}, {} shared - data {} - downloaded {})",
+        HumanBytes(usage.build + usage.data + usage.staged),
+        HumanBytes(usage.build),
+        HumanBytes(usage.shared),
+        HumanBytes(usage.data),
+        HumanBytes(usage.staged)
+    )
+}
+
+/// e.g. `downloading 'default' (10 MB/200 MB, limited to 1 MB/sec)`
+pub fn activity_text(activity: &Activity) -> String {
+    use indicatif::HumanBytes;
+
+    match activity {
+        Activity::Idle => "idle".to_owned(),
+        Activity::Checking => "checking for updates".to_owned(),
+        Activity::Downloading {
+            profile,
+            downloaded,
+            total,
+            rate_limit,
+        } => format!(
+            "downloading '{}' ({}/{}{})",
+            profile,
+            HumanBytes(*downloaded),
+            HumanBytes(*total),
+            rate_limit
+                .map(|x| format!(", limited to {}/sec", HumanBytes(x)))
+                .unwrap_or_default()
+        ),
+    }
+}
+
+/// Prints the event either as JSON or human readable.
+pub fn emit(event: Event) {
+    publish(&event);
+    if is_json() {
+        // The terminal interface uses the console at the moment
+        if !logger::is_console_muted() {
+            println!("{}", to_json(&event));
+        }
+        return;
+    }
+
+    match event {
+        Event::UpdateFound { .. } => log::info!("Update found."),
+        Event::UpToDate { .. } => log::info!("Profile already up-to-date."),
+        Event::AlreadyInstalled { profile, .. } => log::info!(
+            "'{}' is already installed. Use `update` to update it or `--force` to reinstall it.",
+            profile
+        ),
+        Event::DownloadStarted { .. } => log::info!("Updating..."),
+        // Shown as progress bar instead
+        Event::DownloadProgress { .. } => {}
+        Event::Installing { .. } => log::info!("Extracting..."),
+        // Shown as progress bar instead
+        Event::InstallProgress { .. } => {}
+        Event::Installed { .. } => log::info!("Done!"),
+        Event::Launching { .. } => log::info!("Starting..."),
+        Event::Versions { channels } => {
+            for (channel, version) in channels {
+                log::info!("{}: {}", channel, version);
+            }
+        }
+        Event::Profiles { profiles } => {
+            for profile in profiles {
+                log::info!(
+                    "{} {} ({}) - {}",
+                    profile.marker(),
+                    profile.name,
+                    profile.channel,
+                    profile.installed()
+                );
+            }
+        }
+        Event::Status { profiles, daemon } => {
+            for profile in profiles {
+                log::info!(
+                    "{} {} ({}) - installed: {} - latest: {} - size: {} - last launch: {}",
+                    profile.marker(),
+                    profile.name,
+                    profile.channel,
+                    profile.installed(),
+                    profile.latest.as_deref().unwrap_or("unknown"),
+                    disk_usage_text(&profile.disk_breakdown),
+                    profile
+                        .last_launch
+                        .map(|x| x.format("%Y-%m-%d %H:%M").to_string())
+                        .unwrap_or_else(|| "never".into())
+                );
+            }
+            match daemon {
+                Some(daemon) => {
+                    log::info!(
+                        "Daemon: running (pid {}) - {}",
+                        daemon.pid,
+                        activity_text(&daemon.activity)
+                    );
+                    for build in daemon.staged {
+                        log::info!(
+                            "  {} is ready to be installed for '{}' ({})",
+                            build.version,
+                            build.profile,
+                            indicatif::HumanBytes(build.size)
+                        );
+                    }
+                    if let Some(error) = daemon.last_error {
+                        log::info!("  last check failed: {}", error);
+                    }
+                    if let Some(reason) = daemon.cancelled {
+                        log::info!("  last download got cancelled: {}", reason);
+                    }
+                }
+                None => log::info!("Daemon: not running"),
+            }
+        }
+        Event::ProfileCreated { profile, .. } => log::info!(
+            "Created profile '{}'. Use `airshipper --profile {} install` to install it.",
+            profile,
+            profile
+        ),
+        Event::ProfileRemoved { profile, freed, .. } => log::info!(
+            "Removed profile '{}' ({} freed).",
+            profile,
+            indicatif::HumanBytes(freed)
+        ),
+        Event::ProfileActivated { profile } => {
+            log::info!("'{}' is now the active profile.", profile)
+        }
+        Event::Favourites { profile, servers } => {
+            if servers.is_empty() {
+                log::info!("No favourite servers for '{}' yet.", profile);
+            }
+            for server in servers {
+                log::info!("{}", server);
+            }
+        }
+        Event::FavouriteAdded {
+            profile,
+            server,
+            added,
+        } => {
+            if added {
+                log::info!("Added {} to the favourites of '{}'.", server, profile);
+            } else {
+                log::info!("{} is already a favourite.", server);
+            }
+        }
+        Event::FavouriteRemoved {
+            profile,
+            server,
+            removed,
+        } => {
+            if removed {
+                log::info!("Removed {} from the favourites of '{}'.", server, profile);
+            } else {
+                log::info!("{} is not a favourite.", server);
+            }
+        }
+        Event::Config { values } => {
+            for (key, value) in values {
+                log::info!("{} = {}", key, value);
+            }
+        }
+        Event::LogFile { path } => println!("{}", path),
+        Event::Logs { lines, .. } => {
+            for line in lines {
+                println!("{}", line);
+            }
+        }
+        Event::Cleaned { freed, skipped } => {
+            log::info!(
+                "Removed {} of cached and unused data.",
+                indicatif::HumanBytes(freed)
+            );
+            if !skipped.is_empty() {
+                log::info!(
+                    "Kept the downloads of {} as they are in use.",
+                    skipped.join(", ")
+                );
+            }
+        }
+        Event::Changelog { sections } => {
+            for section in sections {
+                println!("{}", section.version);
+                println!("{}", "=".repeat(section.version.len()));
+                for line in section.changes.lines() {
+                    // Headings like `### Added`
+                    println!("{}", line.trim_start_matches('#').trim_start());
+                }
+                println!();
+            }
+        }
+        Event::News { posts } => {
+            for post in posts {
+                println!("{}", post.title);
+                println!("{}", "=".repeat(post.title.chars().count()));
+                println!("{}", post.description.trim());
+                println!("Read more: {}", post.button_url);
+                println!();
+            }
+        }
+        Event::Doctor {
+            version,
+            os,
+            arch,
+            portable,
+            data_path,
+            config_path,
+            cache_path,
+            log_path,
+            checks,
+        } => {
+            log::info!("Please attach everything between the lines to your bug report.");
+            println!("----------");
+            println!("Airshipper v{} on {} ({})", version, os, arch);
+            println!("Portable: {}", portable);
+            println!("Data: {}", data_path);
+            println!("Config: {}", config_path);
+            println!("Cache: {}", cache_path);
+            println!("Log: {}", log_path);
+            println!();
+            for check in checks {
+                println!("[{}] {}: {}", check.status, check.name, check.details);
+            }
+            println!("----------");
+        }
+        Event::Done => {}
+        Event::Error { message, .. } => log::error!("{}", message),
+    }
+}
+
+/// Reports an error which aborted the command.
+pub fn error(error: &ClientError) {
+    emit(Event::Error {
+        code: error.exit_code(),
+        message: error.to_string(),
+    });
+}
@@ -0,0 +1,183 @@
+//! Runs in the background and downloads new builds of all profiles ahead of time,
+//! so updating later only needs to extract them.
+//!
+//! The daemon never touches the game files and doesn't take the launcher lock,
+//! the launcher can be used as usual while it is running.
+
+use crate::{
+    filesystem,
+    lock::Lock,
+    network,
+    profiles::Profile,
+    state::{Activity, DaemonStatus, Settings, StagedBuild},
+    Result,
+};
+use indicatif::HumanBytes;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+/// How often the status gets updated while downloading
+const STATUS_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Checks for updates every `interval` until airshipper gets closed.
+/// Downloads are kept below `max_rate` bytes per second and the limit of the settings.
+pub async fn run(interval: Duration, max_rate: Option<u64>) -> Result<()> {
+    let _lock = match Lock::acquire(filesystem::get_daemon_lock_path())? {
+        Some(lock) => lock,
+        None => return Err("The daemon is already running!".into()),
+    };
+    log::info!(
+        "Checking for updates every {} minutes.",
+        interval.as_secs() / 60
+    );
+    if let Some(max_rate) = max_rate {
+        log::info!("Downloads are limited to {}/sec.", HumanBytes(max_rate));
+    }
+
+    let mut status = DaemonStatus::new();
+    loop {
+        status.activity = Activity::Checking;
+        status.cancelled = None;
+        status.save().await?;
+
+        status.last_error = match check(&mut status, max_rate).await {
+            Ok(errors) if errors.is_empty() => None,
+            Ok(errors) => Some(errors.join("; ")),
+            Err(e) => {
+                log::error!("Failed to check for updates: {}", e);
+                Some(e.to_string())
+            }
+        };
+
+        let now = chrono::Utc::now().timestamp();
+        status.last_check = Some(now);
+        status.next_check = Some(now + interval.as_secs() as i64);
+        status.activity = Activity::Idle;
+        status.save().await?;
+
+        async_std::task::sleep(interval).await;
+    }
+}
+
+/// Pre-downloads the latest build of every installed profile.
+/// Returns what went wrong for each profile which couldn't be checked or downloaded.
+async fn check(status: &mut DaemonStatus, max_rate: Option<u64>) -> Result<Vec<String>> {
+    // Profiles might have been changed meanwhile
+    let (settings, _) = Settings::load().await?;
+    status.staged = staged_builds(&settings.profiles);
+    if let Some(window) = settings.network.download_window {
+        if !window.is_open() {
+            log::info!("Not downloading anything outside of {}.", window);
+            return Ok(Vec::new());
+        }
+    }
+
+    let mut errors = Vec::new();
+    for profile in settings.profiles.iter().filter(|x| x.is_installed()) {
+        // Others (e.g. of another channel) might still work
+        if let Err(e) = check_profile(profile, status, max_rate).await {
+            log::error!("Failed to update '{}': {}", profile.name, e);
+            errors.push(format!("{}: {}", profile.name, e));
+        }
+        status.staged = staged_builds(&settings.profiles);
+    }
+    Ok(errors)
+}
+
+/// Pre-downloads the latest build of the profile unless it's installed or staged already.
+async fn check_profile(
+    profile: &Profile,
+    status: &mut DaemonStatus,
+    max_rate: Option<u64>,
+) -> Result<()> {
+    let latest = profile.check_for_update().await?;
+    if latest == profile.version || profile.staged_version().as_ref() == Some(&latest) {
+        return Ok(());
+    }
+    if profile.is_busy() {
+        log::info!("Skipping '{}' as it is in use.", profile.name);
+        return Ok(());
+    }
+
+    log::info!("Pre-downloading {} for '{}'...", latest, profile.name);
+    if predownload(profile, status, max_rate).await? {
+        // Not necessarily `latest` as a newer build might have been released meanwhile
+        if let Some(version) = profile.staged_version() {
+            log::info!(
+                "{} is ready to be installed for '{}'.",
+                version,
+                profile.name
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Downloads the latest build while reporting the progress.
+/// Returns false if the download got cancelled because the game got started.
+async fn predownload(
+    profile: &Profile,
+    status: &mut DaemonStatus,
+    max_rate: Option<u64>,
+) -> Result<bool> {
+    let cancel = Arc::new(AtomicBool::new(false));
+    let (progress, mut task) = network::download(profile, max_rate, cancel.clone()).await?;
+
+    loop {
+        match async_std::future::timeout(STATUS_INTERVAL, &mut task).await {
+            Ok(result) => {
+                return match result {
+                    Ok(()) => Ok(true),
+                    Err(_) if cancel.load(Ordering::Relaxed) => Ok(false),
+                    Err(e) => Err(e),
+                }
+            }
+            Err(_) => {
+                // Don't steal bandwidth from the game
+                if profile.is_busy() {
+                    log::info!("Cancelling pre-download as '{}' got started.", profile.name);
+                    status.cancelled = Some(format!("'{}' is in use", profile.name));
+                    cancel.store(true, Ordering::Relaxed);
+                }
+                // Applies changes of the limit and the window to the running download
+                if let Ok(settings) = Settings::read().await {
+                    network::set_max_rate(settings.network.max_rate);
+                    match settings.network.download_window {
+                        Some(window) if !window.is_open() => {
+                            log::info!("Cancelling pre-download as {} is over.", window);
+                            status.cancelled = Some(format!("{} is over", window));
+                            cancel.store(true, Ordering::Relaxed);
+                        }
+                        _ => {}
+                    }
+                }
+                let (downloaded, total) = progress.download_progress();
+                status.activity = Activity::Downloading {
+                    profile: profile.name.clone(),
+                    downloaded,
+                    total,
+                    rate_limit: progress.rate_limit(),
+                };
+                status.save().await?;
+            }
+        }
+    }
+}
+
+fn staged_builds(profiles: &[Profile]) -> Vec<StagedBuild> {
+    profiles
+        .iter()
+        .filter_map(|profile| {
+            profile.staged_version().map(|version| StagedBuild {
+                profile: profile.name.clone(),
+                version,
+                size: profile.disk_usage().staged,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,31 @@
+use {
+    crate::cli::control::{self, Notification},
+    futures::stream::{BoxStream, StreamExt},
+    iced_native::subscription::Recipe,
+    std::hash::Hash,
+};
+
+/// Tells about the operations of programs using the control interface.
+pub fn notifications() -> iced::Subscription<Notification> {
+    iced::Subscription::from_recipe(Notifications)
+}
+
+struct Notifications;
+
+impl<H, I> Recipe<H, I> for Notifications
+where
+    H: std::hash::Hasher,
+{
+    type Output = Notification;
+
+    fn hash(&self, state: &mut H) {
+        std::any::TypeId::of::<Self>().hash(state);
+    }
+
+    fn stream(
+        self: Box<Self>,
+        _input: futures::stream::BoxStream<'static, I>,
+    ) -> BoxStream<'static, Self::Output> {
+        control::notifications().boxed()
+    }
+}
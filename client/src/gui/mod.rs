@@ -1,14 +1,16 @@
+mod control;
 mod style;
 mod time;
-mod update;
 
-use crate::{error::ClientError, filesystem, profiles::Profile, state::SavedState, Result};
+use crate::{
+    filesystem,
+    launcher::{Action, Interaction, Launcher, LauncherState, Message},
+};
 use iced::{
     button, scrollable, Align, Application, Button, Column, Command, Container, Element,
     HorizontalAlignment, Image, Length, ProgressBar, Row, Scrollable, Settings, Subscription, Text,
     VerticalAlignment,
 };
-use indicatif::HumanBytes;
 use std::time::Duration;
 
 /// Starts the GUI and won't return
@@ -20,85 +22,42 @@ pub fn run() {
     Airshipper::run(settings);
 }
 
-#[derive(Debug)]
-pub enum LauncherState {
-    LoadingSave,
-    QueryingForUpdates,
-    UpdateAvailable,
-    ReadyToPlay,
-    Downloading(isahc::Metrics),
-    Installing,
-    Playing,
-
-    Error(ClientError),
-}
-
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct Airshipper {
-    /// Current state the GUI is in (e.g. Loading up the save file, updating veloren, ...)
-    state: LauncherState,
-    /// Persistent state which needs to get saved to disk
-    saveable_state: SavedState,
+    launcher: Launcher,
 
     /// Other unrelated state
     changelog_scrollable_state: scrollable::State,
     news_scrollable_state: scrollable::State,
     play_button_state: button::State,
-
-    saving: bool,
-}
-
-impl Default for Airshipper {
-    fn default() -> Self {
-        Self {
-            state: LauncherState::LoadingSave,
-            saveable_state: SavedState::empty(),
-            changelog_scrollable_state: Default::default(),
-            news_scrollable_state: Default::default(),
-            play_button_state: Default::default(),
-
-            saving: false,
-        }
-    }
+    rate_limit_button_state: button::State,
+    /// One for every news post
+    news_buttons: Vec<button::State>,
+    /// One for every favourite server of the active profile
+    favourite_buttons: Vec<button::State>,
 }
 
-impl Airshipper {
-    fn into_save(&self) -> SavedState {
-        self.saveable_state.clone()
-    }
-    fn update_from_save(&mut self, save: SavedState) {
-        self.saveable_state = save;
+/// Turns the work requested by the launcher into commands executed by iced.
+fn into_command(action: Action) -> Command<Message> {
+    match action {
+        Action::None => Command::none(),
+        Action::Perform(future) => Command::perform(future, |message| message),
+        Action::Batch(actions) => Command::batch(actions.into_iter().map(into_command)),
     }
 }
 
-#[derive(Debug)]
-pub enum Message {
-    Interaction(Interaction),
-    Loaded(Result<SavedState>),
-    Saved(Result<()>),
-    UpdateCheckDone(Result<Option<(SavedState, bool)>>),
-    Tick(()), // TODO: Get rid of Tick by implementing download via subscription
-    InstallDone(Result<Profile>),
-    PlayDone(Result<()>),
-    Error(ClientError),
-}
-
-#[derive(Debug, Clone)]
-pub enum Interaction {
-    PlayPressed,
-    ReadMore(String),
-    // Interaction won't do anything
-    Disabled,
-}
-
 impl Application for Airshipper {
     type Executor = iced_futures::executor::AsyncStd;
     type Message = Message;
 
     fn new() -> (Self, Command<Message>) {
+        let (launcher, action) = Launcher::new();
         (
-            Airshipper::default(),
-            Command::perform(SavedState::load(), Message::Loaded),
+            Self {
+                launcher,
+                ..Default::default()
+            },
+            into_command(action),
         )
     }
 
@@ -107,19 +66,19 @@ impl Application for Airshipper {
     }
 
     fn subscription(&self) -> Subscription<Message> {
-        match self.state {
-            LauncherState::Downloading(_) => {
-                time::every(Duration::from_millis(300)).map(Message::Tick)
-            }
-            _ => Subscription::none(),
+        let notifications = control::notifications().map(Message::Control);
+        if self.launcher.needs_tick() {
+            Subscription::batch(vec![
+                notifications,
+                time::every(Duration::from_millis(300)).map(Message::Tick),
+            ])
+        } else {
+            notifications
         }
     }
 
     fn update(&mut self, message: Message) -> Command<Message> {
-        match update::handle_message(self, message) {
-            Ok(x) => x,
-            Err(e) => Command::perform(async { e }, Message::Error),
-        }
+        into_command(self.launcher.update(message))
     }
 
     fn view(&mut self) -> Element<Message> {
@@ -150,7 +109,7 @@ impl Application for Airshipper {
             .height(Length::Fill)
             .padding(15)
             .spacing(20)
-            .push(Text::new(&self.saveable_state.changelog).size(18));
+            .push(Text::new(&self.launcher.saveable_state.cache.changelog).size(18));
 
         // Contains title, changelog
         let left = Column::new()
@@ -164,11 +123,13 @@ impl Application for Airshipper {
             .spacing(20)
             .padding(25);
 
-        for post in &mut self.saveable_state.news {
+        let posts = &self.launcher.saveable_state.cache.news;
+        self.news_buttons.resize_with(posts.len(), Default::default);
+        for (post, btn_state) in posts.iter().zip(&mut self.news_buttons) {
             news = news.push(Text::new(post.title.clone()).size(20));
             news = news.push(Text::new(post.description.clone()).size(16));
             let read_more_btn: Element<Interaction> = Button::new(
-                &mut post.btn_state,
+                btn_state,
                 Text::new("Read More")
                     .size(14)
                     .horizontal_alignment(HorizontalAlignment::Center)
@@ -194,49 +155,40 @@ impl Application for Airshipper {
             .height(Length::FillPortion(6))
             .style(style::Middle);
 
-        let download_text = match &self.state {
-            LauncherState::Downloading(m) => format!(
-                "Downloading... {}/sec",
-                HumanBytes(m.download_speed() as u64)
-            ),
-            LauncherState::Installing => "Installing...".into(),
-            LauncherState::LoadingSave => "Loading...".into(),
-            LauncherState::QueryingForUpdates => "Checking for updates...".into(),
-            LauncherState::ReadyToPlay => "Ready to play...".into(),
-            LauncherState::UpdateAvailable => "Update available!".into(),
-            LauncherState::Playing => "Much fun playing!".into(),
-            LauncherState::Error(e) => e.to_string(),
-        };
-        let download_progress = match &self.state {
-            LauncherState::Downloading(m) => {
-                // Percentage of completed download
-                ((m.download_progress().0 * 100) / m.download_progress().1) as f32
-            }
-            _ => 0.0,
-        };
-        let play_button_text = match &self.state {
-            LauncherState::Downloading(_) => format!("Downloading"),
-            LauncherState::Installing => "Installing".into(),
-            LauncherState::LoadingSave => "Loading".into(),
-            LauncherState::QueryingForUpdates => "Loading".into(),
-            LauncherState::ReadyToPlay => "Play".into(),
-            LauncherState::UpdateAvailable => "Update".into(),
-            LauncherState::Playing => "Playing".into(),
-            LauncherState::Error(_) => "ERROR".into(),
-        };
+        let download_text = self.launcher.status_text();
+        let download_progress = self.launcher.download_progress();
+        let play_button_text = self.launcher.play_button_text();
 
-        let download_speed = Text::new(&download_text).size(16);
+        let download_speed = match &self.launcher.saveable_state.notice {
+            Some(notice) => Text::new(format!("{}\n{}", notice, download_text)),
+            None => Text::new(&download_text),
+        }
+        .size(16);
         let download_progressbar =
             ProgressBar::new(0.0..=100.0, download_progress).style(style::Progress);
+        let rate_limit: Element<Interaction> = Button::new(
+            &mut self.rate_limit_button_state,
+            Text::new(self.launcher.rate_limit_text()).size(14),
+        )
+        .on_press(Interaction::SetRateLimit(self.launcher.next_rate_limit(1)))
+        .padding(2)
+        .style(style::ReadMoreButton)
+        .into();
+        let status = Row::new()
+            .align_items(Align::End)
+            .push(download_speed.width(Length::Fill))
+            .push(rate_limit.map(Message::Interaction));
         let download = Column::new()
             .width(Length::FillPortion(4))
             .spacing(5)
-            .push(download_speed)
-            .push(download_progressbar);
+            .push(status)
+            .push(download_progressbar)
+            .push(Text::new(self.launcher.disk_usage_text()).size(14))
+            .push(Text::new(self.launcher.daemon_text()).size(14));
 
         let mut play = Button::new(
             &mut self.play_button_state,
-            Text::new(&play_button_text)
+            Text::new(play_button_text)
                 .size(30)
                 .height(Length::Fill)
                 .horizontal_alignment(HorizontalAlignment::Center)
@@ -249,24 +201,53 @@ impl Application for Airshipper {
         .padding(2);
 
         // Disable button if loading, playing or downloading the game.
-        match self.state {
-            LauncherState::LoadingSave
-            | LauncherState::Playing
-            | LauncherState::Downloading(_)
-            | LauncherState::QueryingForUpdates
-            | LauncherState::Error(_) => {
-                play = play.style(style::PlayButtonDisabled);
-                play = play.on_press(Interaction::Disabled);
-            }
-            _ => {}
+        if !self.launcher.can_play() {
+            play = play.style(style::PlayButtonDisabled);
+            play = play.on_press(Interaction::Disabled);
         }
         let play: Element<Interaction> = play.into();
 
+        // Favourite servers which can be joined directly
+        let ready_to_play = match self.launcher.state {
+            LauncherState::ReadyToPlay => true,
+            _ => false,
+        };
+        let mut favourites = Column::new().width(Length::FillPortion(1)).spacing(5);
+        let servers = &self
+            .launcher
+            .saveable_state
+            .settings
+            .active_profile()
+            .favourite_servers;
+        self.favourite_buttons
+            .resize_with(servers.len(), Default::default);
+        for (server, btn_state) in servers.iter().zip(&mut self.favourite_buttons) {
+            let address = server.to_string();
+            let mut connect = Button::new(
+                btn_state,
+                Text::new(address.clone())
+                    .size(14)
+                    .horizontal_alignment(HorizontalAlignment::Center)
+                    .vertical_alignment(VerticalAlignment::Center),
+            )
+            .on_press(Interaction::QuickConnect(address))
+            .width(Length::Fill)
+            .height(Length::Units(25))
+            .padding(2)
+            .style(style::ReadMoreButton);
+            if !ready_to_play {
+                connect = connect.on_press(Interaction::Disabled);
+            }
+            favourites = favourites.push(connect);
+        }
+        let favourites: Element<Interaction> = favourites.into();
+
         let bottom = Row::new()
             .align_items(Align::End)
             .spacing(20)
             .padding(10)
             .push(download)
+            .push(favourites.map(Message::Interaction))
             .push(play.map(Message::Interaction));
         let bottom_container = Container::new(bottom).style(style::Bottom);
 
@@ -0,0 +1,306 @@
+//! State machine behind the graphical and the terminal user interface.
+//!
+//! Both frontends only render [`Launcher`] and turn user input into [`Message`]s.
+//! Work which takes a while is returned as an [`Action`] for the frontend to run,
+//! its result is fed back as another [`Message`].
+
+mod update;
+
+use crate::{
+    cli::{
+        control::{self, Notification},
+        output,
+    },
+    error::ClientError,
+    network,
+    profiles::{DiskUsage, Profile},
+    state::{DaemonStatus, SavedState},
+    Result,
+};
+use futures::future::{BoxFuture, Future, FutureExt};
+use indicatif::HumanBytes;
+
+pub use update::handle_message;
+
+/// Download limits the user can switch between in bytes per second, `None` is unlimited
+pub const RATE_LIMITS: &[Option<u64>] = &[
+    None,
+    Some(256 * 1024),
+    Some(512 * 1024),
+    Some(1024 * 1024),
+    Some(2 * 1024 * 1024),
+    Some(5 * 1024 * 1024),
+    Some(10 * 1024 * 1024),
+];
+
+#[derive(Debug)]
+pub enum LauncherState {
+    LoadingSave,
+    QueryingForUpdates,
+    UpdateAvailable,
+    ReadyToPlay,
+    Downloading(network::Progress),
+    Installing(network::InstallProgress),
+    Playing,
+    /// Another program works on the profiles via the control interface
+    Controlled,
+
+    Error(ClientError),
+}
+
+#[derive(Debug)]
+pub struct Launcher {
+    /// Current state the launcher is in (e.g. Loading up the save file, updating veloren, ...)
+    pub state: LauncherState,
+    /// Persistent state which needs to get saved to disk
+    pub saveable_state: SavedState,
+
+    pub saving: bool,
+    /// The available update has been downloaded by the daemon already
+    pub update_staged: bool,
+    /// Space taken up by the active profile, `None` until it has been measured
+    pub disk_usage: Option<DiskUsage>,
+    /// What the daemon is doing, `None` if it isn't running
+    pub daemon: Option<DaemonStatus>,
+    /// Whether the status of the daemon gets reloaded regularly
+    watching_daemon: bool,
+}
+
+impl Default for Launcher {
+    fn default() -> Self {
+        Self {
+            state: LauncherState::LoadingSave,
+            saveable_state: SavedState::empty(),
+
+            saving: false,
+            update_staged: false,
+            disk_usage: None,
+            daemon: None,
+            watching_daemon: false,
+        }
+    }
+}
+
+impl Launcher {
+    /// Returns the launcher together with the action loading the saved state.
+    pub fn new() -> (Self, Action) {
+        (
+            Self::default(),
+            Action::perform(SavedState::load(), Message::Loaded),
+        )
+    }
+
+    /// Handles the message, errors get turned into the error state.
+    pub fn update(&mut self, message: Message) -> Action {
+        let action = match handle_message(self, message) {
+            Ok(x) => x,
+            Err(e) => Action::perform(async { e }, Message::Error),
+        };
+        // The state would get saved on top of the changes of the control interface
+        control::set_frontend_busy(!self.can_switch_profile());
+        action
+    }
+
+    /// Whether the frontend has to send [`Message::Tick`] regularly.
+    pub fn needs_tick(&self) -> bool {
+        matches!(
+            self.state,
+            LauncherState::Downloading(_) | LauncherState::Installing(_)
+        )
+    }
+
+    /// Percentage of the completed download or install
+    pub fn download_progress(&self) -> f32 {
+        match &self.state {
+            LauncherState::Downloading(m) => match m.download_progress() {
+                // Nothing is known until the server responded
+                (_, 0) => 0.0,
+                (downloaded, total) => ((downloaded * 100) / total) as f32,
+            },
+            LauncherState::Installing(m) => match m.files() {
+                (_, 0) => 0.0,
+                (files, total) => ((files * 100) / total) as f32,
+            },
+            _ => 0.0,
+        }
+    }
+
+    /// Describes what the launcher is doing at the moment.
+    pub fn status_text(&self) -> String {
+        match &self.state {
+            LauncherState::Downloading(m) => match m.rate_limit() {
+                Some(limit) => format!(
+                    "Downloading... {}/sec (limited to {}/sec)",
+                    HumanBytes(m.download_speed() as u64),
+                    HumanBytes(limit)
+                ),
+                None => format!(
+                    "Downloading... {}/sec",
+                    HumanBytes(m.download_speed() as u64)
+                ),
+            },
+            LauncherState::Installing(m) => match m.files() {
+                (_, 0) => "Installing...".into(),
+                (files, total) => format!(
+                    "Installing... {}/{} files ({})",
+                    files,
+                    total,
+                    m.current_file()
+                ),
+            },
+            LauncherState::LoadingSave => "Loading...".into(),
+            LauncherState::QueryingForUpdates => "Checking for updates...".into(),
+            LauncherState::ReadyToPlay => "Ready to play...".into(),
+            LauncherState::UpdateAvailable => "Update available!".into(),
+            LauncherState::Playing => "Much fun playing!".into(),
+            LauncherState::Controlled => "Busy with a request of another program...".into(),
+            LauncherState::Error(e) => e.to_string(),
+        }
+    }
+
+    /// Describes the download limit set by the user.
+    pub fn rate_limit_text(&self) -> String {
+        match self.saveable_state.settings.network.max_rate {
+            Some(limit) => format!("Limit: {}/sec", HumanBytes(limit)),
+            None => "Limit: none".into(),
+        }
+    }
+
+    /// Describes the space taken up by the active profile.
+    pub fn disk_usage_text(&self) -> String {
+        match &self.disk_usage {
+            Some(usage) => format!(
+                "Disk usage: {} (build {}, {} of it shared, data {}, downloaded {})",
+                HumanBytes(usage.build + usage.data + usage.staged),
+                HumanBytes(usage.build),
+                HumanBytes(usage.shared),
+                HumanBytes(usage.data),
+                HumanBytes(usage.staged)
+            ),
+            None => String::new(),
+        }
+    }
+
+    /// Describes what the daemon is doing, empty if it isn't running.
+    pub fn daemon_text(&self) -> String {
+        let daemon = match &self.daemon {
+            Some(daemon) => daemon,
+            None => return String::new(),
+        };
+        let mut text = format!("Daemon: {}", output::activity_text(&daemon.activity));
+        let active = &self.saveable_state.settings.active_profile;
+        if let Some(build) = daemon.staged.iter().find(|x| &x.profile == active) {
+            text += &format!(" - {} is downloaded", build.version);
+        }
+        if let Some(reason) = &daemon.cancelled {
+            text += &format!(" - last download cancelled as {}", reason);
+        }
+        text
+    }
+
+    /// Returns the limit `steps` entries of [`RATE_LIMITS`] away from the current one.
+    pub fn next_rate_limit(&self, steps: isize) -> Option<u64> {
+        let current = RATE_LIMITS
+            .iter()
+            .position(|x| *x == self.saveable_state.settings.network.max_rate)
+            .unwrap_or(0) as isize;
+        let len = RATE_LIMITS.len() as isize;
+        RATE_LIMITS[(current + steps).rem_euclid(len) as usize]
+    }
+
+    /// Label of the play button.
+    pub fn play_button_text(&self) -> &'static str {
+        match &self.state {
+            LauncherState::Downloading(_) => "Downloading",
+            LauncherState::Installing(_) => "Installing",
+            LauncherState::LoadingSave => "Loading",
+            LauncherState::QueryingForUpdates => "Loading",
+            LauncherState::ReadyToPlay => "Play",
+            LauncherState::UpdateAvailable => "Update",
+            LauncherState::Playing => "Playing",
+            LauncherState::Controlled => "Busy",
+            LauncherState::Error(_) => "ERROR",
+        }
+    }
+
+    /// Whether pressing the play button does anything.
+    pub fn can_play(&self) -> bool {
+        matches!(
+            self.state,
+            LauncherState::ReadyToPlay
+                | LauncherState::UpdateAvailable
+                | LauncherState::Installing(_)
+        )
+    }
+
+    /// Whether the user can change the active profile at the moment.
+    pub fn can_switch_profile(&self) -> bool {
+        matches!(
+            self.state,
+            LauncherState::ReadyToPlay | LauncherState::UpdateAvailable | LauncherState::Error(_)
+        )
+    }
+
+    fn into_save(&self) -> SavedState {
+        self.saveable_state.clone()
+    }
+
+    fn update_from_save(&mut self, save: SavedState) {
+        self.saveable_state = save;
+    }
+}
+
+#[derive(Debug)]
+pub enum Message {
+    Interaction(Interaction),
+    Loaded(Result<SavedState>),
+    Saved(Result<()>),
+    UpdateCheckDone(Result<Option<(SavedState, bool, bool)>>),
+    DownloadStarted(Result<network::Progress>),
+    Tick(()), // TODO: Get rid of Tick by implementing download via subscription
+    InstallDone(Result<Profile>),
+    DiskUsageMeasured(DiskUsage),
+    DaemonStatus(Option<DaemonStatus>),
+    PlayDone(Result<()>),
+    Control(Notification),
+    Error(ClientError),
+}
+
+#[derive(Debug, Clone)]
+pub enum Interaction {
+    PlayPressed,
+    /// Start the game and join the server with the given address
+    QuickConnect(String),
+    ReadMore(String),
+    /// Make the profile with the given name the active one
+    SelectProfile(String),
+    /// Change the download limit (bytes per second), applies to the running download too
+    SetRateLimit(Option<u64>),
+    // Interaction won't do anything
+    Disabled,
+}
+
+/// Work the frontend has to run in the background.
+pub enum Action {
+    None,
+    Perform(BoxFuture<'static, Message>),
+    Batch(Vec<Action>),
+}
+
+impl Action {
+    pub fn perform<T, F>(future: F, f: impl FnOnce(T) -> Message + Send + 'static) -> Self
+    where
+        F: Future<Output = T> + Send + 'static,
+    {
+        Action::Perform(future.map(f).boxed())
+    }
+
+    /// Returns all futures which have to be run.
+    pub fn into_futures(self) -> Vec<BoxFuture<'static, Message>> {
+        match self {
+            Action::None => Vec::new(),
+            Action::Perform(future) => vec![future],
+            Action::Batch(actions) => actions.into_iter().flat_map(Action::into_futures).collect(),
+        }
+    }
+}
@@ -0,0 +1,300 @@
+use {
+    super::{Action, Interaction, Launcher, LauncherState, Message, Notification, SavedState},
+    crate::{
+        cli::output::{self, Event},
+        network,
+        profiles::{Profile, Server},
+        state::DaemonStatus,
+        Result,
+    },
+    std::time::Duration,
+};
+
+/// How often the status of the daemon gets reloaded
+const DAEMON_STATUS_INTERVAL: Duration = Duration::from_secs(2);
+
+pub fn handle_message(airship: &mut Launcher, message: Message) -> Result<Action> {
+    let mut needs_save = false;
+    let mut actions = Vec::new();
+
+    match message {
+        Message::Loaded(saved_state) => {
+            // Never continue with defaults as they would overwrite the user settings
+            airship.update_from_save(saved_state?);
+
+            airship.state = LauncherState::QueryingForUpdates;
+            let mut actions = vec![
+                Action::perform(
+                    check_for_updates(airship.saveable_state.clone()),
+                    Message::UpdateCheckDone,
+                ),
+                measure_disk_usage(airship),
+            ];
+            if !airship.watching_daemon {
+                airship.watching_daemon = true;
+                actions.push(Action::perform(DaemonStatus::load(), Message::DaemonStatus));
+            }
+            return Ok(Action::Batch(actions));
+        }
+        Message::DaemonStatus(status) => {
+            airship.daemon = status;
+            return Ok(Action::perform(
+                async {
+                    async_std::task::sleep(DAEMON_STATUS_INTERVAL).await;
+                    DaemonStatus::load().await
+                },
+                Message::DaemonStatus,
+            ));
+        }
+        Message::Saved(_) => {
+            airship.saving = false;
+        }
+        Message::Interaction(Interaction::PlayPressed) => {
+            if let LauncherState::UpdateAvailable = airship.state {
+                if airship.update_staged {
+                    return Ok(install(airship));
+                }
+                // Shows the download until the server responded
+                airship.state = LauncherState::Downloading(Default::default());
+                output::emit(Event::DownloadStarted {
+                    profile: airship.saveable_state.settings.active_profile.clone(),
+                });
+                return Ok(Action::perform(
+                    start_download(airship.saveable_state.settings.active_profile().clone()),
+                    Message::DownloadStarted,
+                ));
+            } else {
+                match airship.state {
+                    LauncherState::ReadyToPlay => return Ok(play(airship, None)),
+                    _ => {}
+                }
+            }
+        }
+        Message::Interaction(Interaction::QuickConnect(address)) => {
+            if let LauncherState::ReadyToPlay = airship.state {
+                let server = address.parse::<Server>()?;
+                return Ok(play(airship, Some(server)));
+            }
+        }
+        Message::Interaction(Interaction::ReadMore(url)) => {
+            if let Err(e) = opener::open(&url) {
+                return Err(format!("failed to open {} : {}", url, e).into());
+            }
+        }
+        Message::Interaction(Interaction::SelectProfile(name)) => {
+            if airship.can_switch_profile()
+                && name != airship.saveable_state.settings.active_profile
+            {
+                airship.saveable_state.settings.set_active_profile(&name)?;
+                airship.update_staged = false;
+                airship.disk_usage = None;
+                airship.state = LauncherState::QueryingForUpdates;
+                return Ok(Action::Batch(vec![
+                    Action::perform(
+                        check_for_updates(airship.saveable_state.clone()),
+                        Message::UpdateCheckDone,
+                    ),
+                    measure_disk_usage(airship),
+                ]));
+            }
+        }
+        Message::Interaction(Interaction::SetRateLimit(max_rate)) => {
+            airship.saveable_state.settings.network.max_rate = max_rate;
+            network::set_max_rate(max_rate);
+            needs_save = true;
+        }
+        Message::UpdateCheckDone(update) => {
+            match update? {
+                Some((save, profile_update_available, update_staged)) => {
+                    airship.saveable_state = save;
+                    airship.update_staged = update_staged;
+                    if profile_update_available {
+                        airship.state = LauncherState::UpdateAvailable;
+                    } else {
+                        airship.state = LauncherState::ReadyToPlay;
+                    }
+                }
+                None => airship.state = LauncherState::ReadyToPlay,
+            }
+            needs_save = true;
+        }
+        Message::InstallDone(result) => {
+            let profile = result?;
+            output::emit(Event::Installed {
+                profile: profile.name.clone(),
+                version: profile.version.clone(),
+            });
+            *airship.saveable_state.settings.active_profile_mut() = profile;
+            airship.update_staged = false;
+            needs_save = true;
+            airship.state = LauncherState::ReadyToPlay;
+            actions.push(measure_disk_usage(airship));
+        }
+        Message::DiskUsageMeasured(usage) => {
+            airship.disk_usage = Some(usage);
+        }
+        Message::DownloadStarted(progress) => {
+            airship.state = LauncherState::Downloading(progress?);
+        }
+        Message::Tick(_) => {
+            let profile = airship.saveable_state.settings.active_profile.clone();
+            match &airship.state {
+                LauncherState::Downloading(progress) => match progress.finished() {
+                    Some(Ok(())) => {
+                        return Ok(install(airship));
+                    }
+                    Some(Err(e)) => return Err(e),
+                    None => {
+                        let (downloaded, total) = progress.download_progress();
+                        output::emit(Event::DownloadProgress {
+                            profile,
+                            downloaded,
+                            total,
+                            speed: progress.download_speed() as u64,
+                            rate_limit: progress.rate_limit(),
+                        });
+                    }
+                },
+                LauncherState::Installing(progress) => match progress.files() {
+                    (_, 0) => {}
+                    (files, total_files) => output::emit(Event::InstallProgress {
+                        profile,
+                        files,
+                        total_files,
+                        file: progress.current_file(),
+                    }),
+                },
+                _ => {}
+            }
+        }
+        Message::Control(Notification::Started) => {
+            if airship.can_switch_profile() {
+                airship.state = LauncherState::Controlled;
+            }
+        }
+        Message::Control(Notification::Finished) => {
+            // The other program saved its changes
+            if let LauncherState::Controlled = airship.state {
+                airship.state = LauncherState::LoadingSave;
+                return Ok(Action::perform(SavedState::load(), Message::Loaded));
+            }
+        }
+        Message::Error(e) | Message::PlayDone(Err(e)) => {
+            // Programs watching via the control interface
+            output::error(&e);
+            airship.state = LauncherState::Error(e);
+        }
+        // Everything went fine when playing the game :O
+        Message::PlayDone(Ok(())) => {
+            // After playing check for an possible update
+            // useful if you got kicked from the server due to an update so you can instantly update too
+            airship.state = LauncherState::QueryingForUpdates;
+            return Ok(Action::perform(
+                check_for_updates(airship.saveable_state.clone()),
+                Message::UpdateCheckDone,
+            ));
+        }
+        Message::Interaction(Interaction::Disabled) => {}
+    }
+
+    if needs_save && !airship.saving {
+        airship.saving = true;
+        actions.push(Action::perform(airship.into_save().save(), Message::Saved));
+    }
+
+    Ok(match actions.len() {
+        0 => Action::None,
+        1 => actions.remove(0),
+        _ => Action::Batch(actions),
+    })
+}
+
+/// Starts the game and saves when it got launched.
+fn play(airship: &mut Launcher, server: Option<Server>) -> Action {
+    airship.state = LauncherState::Playing;
+    output::emit(Event::Launching {
+        profile: airship.saveable_state.settings.active_profile.clone(),
+        server: server.as_ref().map(|x| x.to_string()),
+    });
+    airship
+        .saveable_state
+        .settings
+        .active_profile_mut()
+        .record_launch();
+    airship.saving = true;
+    Action::Batch(vec![
+        Action::perform(airship.into_save().save(), Message::Saved),
+        Action::perform(
+            start(
+                airship.saveable_state.settings.active_profile().clone(),
+                server,
+            ),
+            Message::PlayDone,
+        ),
+    ])
+}
+
+/// Returns new state if updated.
+/// the first bool signifies whether an profile update is available,
+/// the second one whether it has been downloaded already
+async fn check_for_updates(
+    mut saveable_state: SavedState,
+) -> Result<Option<(SavedState, bool, bool)>> {
+    let mut modified = false;
+    let mut profile_update_available = false;
+    let mut update_staged = false;
+
+    if saveable_state.cache.update_changelog().await? {
+        modified = true;
+    }
+    if saveable_state.cache.update_news().await? {
+        modified = true;
+    }
+
+    let profile = saveable_state.settings.active_profile();
+    let latest = profile.check_for_update().await?;
+    if latest != profile.version {
+        modified = true;
+        profile_update_available = true;
+        update_staged = profile.staged_version() == Some(latest);
+        log::debug!("Found profile update.")
+    }
+
+    Ok(if modified {
+        Some((saveable_state, profile_update_available, update_staged))
+    } else {
+        None
+    })
+}
+
+async fn start_download(profile: Profile) -> Result<network::Progress> {
+    profile.start_download().await
+}
+
+/// Measures the space taken up by the active profile.
+fn measure_disk_usage(airship: &Launcher) -> Action {
+    let profile = airship.saveable_state.settings.active_profile().clone();
+    // Walks through all files of the profile
+    Action::perform(
+        async_std::task::spawn_blocking(move || profile.disk_usage()),
+        Message::DiskUsageMeasured,
+    )
+}
+
+/// Installs the downloaded build while showing which files got extracted.
+fn install(airship: &mut Launcher) -> Action {
+    let progress = network::InstallProgress::default();
+    airship.state = LauncherState::Installing(progress.clone());
+    let profile = airship.saveable_state.settings.active_profile().clone();
+    output::emit(Event::Installing {
+        profile: profile.name.clone(),
+    });
+    Action::perform(
+        profile.install_with_progress(progress),
+        Message::InstallDone,
+    )
+}
+
+async fn start(profile: Profile, server: Option<Server>) -> Result<()> {
+    Ok(profile.start(server.as_ref())?)
+}
@@ -1,8 +1,24 @@
 use crate::{filesystem, Result};
 use fern::colors::{Color, ColoredLevelConfig};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Keeps log messages from being printed to the console (they still end up in the log file)
+static CONSOLE_MUTED: AtomicBool = AtomicBool::new(false);
+
+/// Stops printing log messages to the console, e.g. while it is used by the terminal UI.
+#[cfg(feature = "tui")]
+pub fn mute_console(muted: bool) {
+    CONSOLE_MUTED.store(muted, Ordering::Relaxed);
+}
+
+/// Whether the console is used by something else than the log at the moment.
+pub fn is_console_muted() -> bool {
+    CONSOLE_MUTED.load(Ordering::Relaxed)
+}
 
 /// Setup logging.
-pub fn log(level: log::LevelFilter) -> Result<()> {
+/// Log messages get printed to stderr instead of stdout if `stderr` is set.
+pub fn log(level: log::LevelFilter, stderr: bool) -> Result<()> {
     let colors = ColoredLevelConfig::new()
         .error(Color::Red)
         .warn(Color::Yellow)
@@ -43,7 +59,9 @@ pub fn log(level: log::LevelFilter) -> Result<()> {
         })
         .chain(fern::log_file(&filesystem::get_log_path())?);
 
-    let mut stdout_cfg = fern::Dispatch::new().level(level);
+    let mut stdout_cfg = fern::Dispatch::new()
+        .level(level)
+        .filter(|_| !is_console_muted());
     // If more verbose debugging is requested. We will print the lines too.
     if level == log::LevelFilter::Debug || level == log::LevelFilter::Trace {
         stdout_cfg = stdout_cfg.format(move |out, message, record| {
@@ -68,9 +86,42 @@ pub fn log(level: log::LevelFilter) -> Result<()> {
         });
     }
 
-    stdout_cfg = stdout_cfg.chain(std::io::stdout());
+    stdout_cfg = if stderr {
+        stdout_cfg.chain(std::io::stderr())
+    } else {
+        stdout_cfg.chain(std::io::stdout())
+    };
 
     base.chain(file_cfg).chain(stdout_cfg).apply()?;
 
     Ok(())
 }
+
+pub fn setup_panic_hook() {
+    use std::panic;
+
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |panic_info| {
+        let panic_info_payload = panic_info.payload();
+        let payload_string = panic_info_payload.downcast_ref::<String>();
+        let reason = match payload_string {
+            Some(s) => &s,
+            None => {
+                let payload_str = panic_info_payload.downcast_ref::<&str>();
+                match payload_str {
+                    Some(st) => st,
+                    None => "Payload is not a string",
+                }
+            }
+        };
+
+        log::error!(
+            "Airshipper panicked: {}\nBacktrace:\n{:?}\nLogFile: '{}'",
+            reason,
+            backtrace::Backtrace::new(),
+            filesystem::get_log_path().display(),
+        );
+
+        default_hook(panic_info);
+    }));
+}
@@ -1,25 +1,37 @@
 mod cli;
-mod error;
-mod filesystem;
+mod daemon;
 #[cfg(feature = "gui")]
 mod gui;
+#[cfg(any(feature = "gui", feature = "tui"))]
+mod launcher;
 mod logger;
-mod network;
-mod profiles;
-mod state;
+#[cfg(feature = "tui")]
+mod tui;
 #[cfg(windows)]
 mod updater;
 
-use crate::error::ClientError;
-
-pub type Result<T> = std::result::Result<T, ClientError>;
+// Everything which isn't specific to the user interface lives in the core crate
+use airshipper_core::{
+    error::{self, ClientError},
+    filesystem, lock, network, profiles, state, store, Result,
+};
 
 #[async_std::main]
 async fn main() {
-    error::setup_panic_hook();
+    logger::setup_panic_hook();
     if let Err(e) = cli::process().await {
-        log::error!("{}", e);
-        log::info!("Press enter to exit...");
-        let _ = std::io::stdin().read_line(&mut String::new());
+        let usage_error = matches!(e, ClientError::UsageError(_));
+        if usage_error && !cli::output::is_json() {
+            // Printed like clap does as the logger might not be set up yet
+            eprintln!("{}", e);
+        } else {
+            cli::output::error(&e);
+        }
+        // Keeps the window open long enough to read the error
+        if cli::is_interactive() && !cli::output::is_json() && !usage_error {
+            log::info!("Press enter to exit...");
+            let _ = std::io::stdin().read_line(&mut String::new());
+        }
+        std::process::exit(e.exit_code());
     }
 }
@@ -0,0 +1,242 @@
+//! Full screen terminal interface for systems without a graphical environment.
+//!
+//! It is driven by the same state machine as the GUI (see [`crate::launcher`]),
+//! this module only translates key presses and draws the state.
+
+mod view;
+
+use crate::{
+    cli::control,
+    launcher::{Action, Interaction, Launcher, LauncherState, Message},
+    logger, network,
+    state::SavedState,
+    Result,
+};
+use crossterm::{
+    event::{Event, EventStream, KeyCode, KeyEvent, KeyModifiers},
+    terminal::{EnterAlternateScreen, LeaveAlternateScreen},
+};
+use futures::{channel::mpsc, FutureExt, StreamExt};
+use std::{io::Stdout, time::Duration};
+use tui_rs::{backend::CrosstermBackend, widgets::ListState, Terminal};
+
+/// How often the download progress gets updated
+const TICK: Duration = Duration::from_millis(250);
+
+/// Which pane gets scrolled with the arrow keys
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Pane {
+    Changelog,
+    News,
+}
+
+/// State of the interface which does not belong to the launcher
+#[derive(Debug)]
+struct Ui {
+    focus: Pane,
+    changelog_scroll: u16,
+    news: ListState,
+}
+
+enum Input {
+    Message(Message),
+    Quit,
+    None,
+}
+
+/// Runs the interface until the user quits. `state` gets updated with the changes made meanwhile.
+pub async fn run(state: &mut SavedState) -> Result<()> {
+    let mut launcher = Launcher::default();
+    let (sender, mut messages) = mpsc::unbounded();
+    let mut ui = Ui {
+        focus: Pane::Changelog,
+        changelog_scroll: 0,
+        news: ListState::default(),
+    };
+    let mut events = EventStream::new();
+    let mut notifications = control::notifications();
+    let mut screen = None;
+
+    // The state has been loaded already by the command line
+    let action = launcher.update(Message::Loaded(Ok(state.clone())));
+    perform(action, &sender);
+
+    loop {
+        // The game gets the console to itself while it is running
+        let playing = matches!(launcher.state, LauncherState::Playing);
+        if playing {
+            screen = None;
+        } else if screen.is_none() {
+            screen = Some(Screen::enter()?);
+        }
+        if let Some(screen) = &mut screen {
+            screen
+                .terminal
+                .draw(|frame| view::draw(frame, &launcher, &mut ui))?;
+        }
+
+        let message = futures::select! {
+            message = messages.select_next_some() => Some(message),
+            notification = notifications.select_next_some() => Some(Message::Control(notification)),
+            event = events.next().fuse() => {
+                let event = match event {
+                    Some(event) => event.map_err(|e| e.to_string())?,
+                    None => break,
+                };
+                match handle_event(event, &launcher, &mut ui) {
+                    Input::Message(message) => Some(message),
+                    Input::Quit => break,
+                    Input::None => None,
+                }
+            }
+            _ = async_std::task::sleep(TICK).fuse() => {
+                if launcher.needs_tick() {
+                    Some(Message::Tick(()))
+                } else {
+                    None
+                }
+            }
+        };
+        if let Some(message) = message {
+            perform(launcher.update(message), &sender);
+        }
+    }
+
+    if let LauncherState::Downloading(_) = launcher.state {
+        network::cancel_download();
+    }
+    // Don't race with the save of the command line
+    while launcher.saving {
+        match messages.next().await {
+            Some(message) => perform(launcher.update(message), &sender),
+            None => break,
+        }
+    }
+    drop(screen);
+
+    *state = launcher.saveable_state;
+    Ok(())
+}
+
+/// Runs the futures of the action in the background and sends back their result.
+fn perform(action: Action, sender: &mpsc::UnboundedSender<Message>) {
+    for future in action.into_futures() {
+        let sender = sender.clone();
+        async_std::task::spawn(async move {
+            let _ = sender.unbounded_send(future.await);
+        });
+    }
+}
+
+fn handle_event(event: Event, launcher: &Launcher, ui: &mut Ui) -> Input {
+    let KeyEvent { code, modifiers } = match event {
+        Event::Key(key) => key,
+        _ => return Input::None,
+    };
+    let profiles = &launcher.saveable_state.settings.profiles;
+    let active = &launcher.saveable_state.settings.active_profile;
+    let favourites = &launcher
+        .saveable_state
+        .settings
+        .active_profile()
+        .favourite_servers;
+    let posts = launcher.saveable_state.cache.news.len();
+
+    let interaction = match code {
+        KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => return quit(launcher),
+        KeyCode::Char('q') | KeyCode::Esc => return quit(launcher),
+        KeyCode::Enter | KeyCode::Char(' ') | KeyCode::Char('p') if launcher.can_play() => {
+            Interaction::PlayPressed
+        }
+        KeyCode::Char(x @ '1'..='9') => match favourites.get(x as usize - '1' as usize) {
+            Some(server) => Interaction::QuickConnect(server.to_string()),
+            None => return Input::None,
+        },
+        KeyCode::Left | KeyCode::Right | KeyCode::Char('h') | KeyCode::Char('l') => {
+            let idx = profiles.iter().position(|x| &x.name == active).unwrap_or(0);
+            let idx = match code {
+                KeyCode::Left | KeyCode::Char('h') => idx + profiles.len() - 1,
+                _ => idx + 1,
+            } % profiles.len();
+            Interaction::SelectProfile(profiles[idx].name.clone())
+        }
+        KeyCode::Char('o') => match ui
+            .news
+            .selected()
+            .and_then(|x| launcher.saveable_state.cache.news.get(x))
+        {
+            Some(post) => Interaction::ReadMore(post.button_url.clone()),
+            None => return Input::None,
+        },
+        KeyCode::Char('+') => Interaction::SetRateLimit(launcher.next_rate_limit(1)),
+        KeyCode::Char('-') => Interaction::SetRateLimit(launcher.next_rate_limit(-1)),
+        KeyCode::Tab | KeyCode::BackTab => {
+            ui.focus = match ui.focus {
+                Pane::Changelog => Pane::News,
+                Pane::News => Pane::Changelog,
+            };
+            if ui.focus == Pane::News && ui.news.selected().is_none() && posts > 0 {
+                ui.news.select(Some(0));
+            }
+            return Input::None;
+        }
+        KeyCode::Up | KeyCode::Char('k') => return scroll(ui, posts, -1),
+        KeyCode::Down | KeyCode::Char('j') => return scroll(ui, posts, 1),
+        KeyCode::PageUp => return scroll(ui, posts, -10),
+        KeyCode::PageDown => return scroll(ui, posts, 10),
+        _ => return Input::None,
+    };
+    Input::Message(Message::Interaction(interaction))
+}
+
+fn quit(launcher: &Launcher) -> Input {
+    match launcher.state {
+        // Would leave a broken installation behind
+        LauncherState::Installing(_) => Input::None,
+        _ => Input::Quit,
+    }
+}
+
+fn scroll(ui: &mut Ui, posts: usize, lines: i32) -> Input {
+    match ui.focus {
+        Pane::Changelog => {
+            ui.changelog_scroll = (ui.changelog_scroll as i32 + lines).max(0) as u16;
+        }
+        Pane::News if posts > 0 => {
+            let selected = ui.news.selected().unwrap_or(0) as i32 + lines;
+            ui.news
+                .select(Some(selected.max(0).min(posts as i32 - 1) as usize));
+        }
+        Pane::News => {}
+    }
+    Input::None
+}
+
+/// Terminal in raw mode showing the alternate screen, restored once dropped.
+struct Screen {
+    terminal: Terminal<CrosstermBackend<Stdout>>,
+}
+
+impl Screen {
+    fn enter() -> Result<Self> {
+        // Log messages would end up in the middle of the interface
+        logger::mute_console(true);
+        crossterm::terminal::enable_raw_mode().map_err(|e| e.to_string())?;
+        let mut stdout = std::io::stdout();
+        crossterm::execute!(stdout, EnterAlternateScreen).map_err(|e| e.to_string())?;
+
+        let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+        terminal.hide_cursor()?;
+        terminal.clear()?;
+        Ok(Self { terminal })
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let _ = crossterm::execute!(self.terminal.backend_mut(), LeaveAlternateScreen);
+        let _ = crossterm::terminal::disable_raw_mode();
+        let _ = self.terminal.show_cursor();
+        logger::mute_console(false);
+    }
+}
@@ -0,0 +1,229 @@
+use super::{Pane, Ui};
+use crate::launcher::{Launcher, LauncherState};
+use indicatif::{HumanBytes, HumanDuration};
+use std::time::Duration;
+use tui_rs::{
+    backend::Backend,
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Gauge, List, ListItem, Paragraph, Tabs, Wrap},
+    Frame,
+};
+
+const KEYS: &str = "Enter: play/update  \u{2190}/\u{2192}: profile  Tab: switch pane  \
+                    \u{2191}/\u{2193}: scroll  o: open post  1-9: join favourite  \
+                    +/-: download limit  q: quit";
+
+/// Draws the same information the GUI shows.
+pub(super) fn draw<B: Backend>(frame: &mut Frame<B>, launcher: &Launcher, ui: &mut Ui) {
+    let state = &launcher.saveable_state;
+    let notice_height = if state.notice.is_some() { 1 } else { 0 };
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Length(1 + notice_height),
+                Constraint::Min(5),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(1),
+            ]
+            .as_ref(),
+        )
+        .split(frame.size());
+
+    // Title
+    let mut title = vec![Spans::from(Span::styled(
+        format!("Airshipper v{}", env!("CARGO_PKG_VERSION")),
+        Style::default().add_modifier(Modifier::BOLD),
+    ))];
+    if let Some(notice) = &state.notice {
+        title.push(Spans::from(Span::styled(
+            notice.as_str(),
+            Style::default().fg(Color::Yellow),
+        )));
+    }
+    let header = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Min(0), Constraint::Min(0)].as_ref())
+        .split(rows[0]);
+    frame.render_widget(Paragraph::new(title), header[0]);
+    frame.render_widget(
+        Paragraph::new(Span::styled(
+            launcher.daemon_text(),
+            Style::default().fg(Color::Gray),
+        ))
+        .alignment(Alignment::Right),
+        header[1],
+    );
+
+    // Changelog and news
+    let middle = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(60), Constraint::Percentage(40)].as_ref())
+        .split(rows[1]);
+
+    let changelog = Paragraph::new(state.cache.changelog.as_str())
+        .block(pane("Changelog", ui.focus == Pane::Changelog))
+        .wrap(Wrap { trim: false })
+        .scroll((ui.changelog_scroll, 0));
+    frame.render_widget(changelog, middle[0]);
+
+    let posts: Vec<ListItem> = state
+        .cache
+        .news
+        .iter()
+        .map(|post| {
+            ListItem::new(vec![
+                Spans::from(Span::styled(
+                    post.title.as_str(),
+                    Style::default().add_modifier(Modifier::BOLD),
+                )),
+                Spans::from(Span::styled(
+                    post.description.as_str(),
+                    Style::default().fg(Color::Gray),
+                )),
+                Spans::default(),
+            ])
+        })
+        .collect();
+    let news = List::new(posts)
+        .block(pane("News", ui.focus == Pane::News))
+        .highlight_style(Style::default().bg(Color::DarkGray));
+    frame.render_stateful_widget(news, middle[1], &mut ui.news);
+
+    // Profile selector
+    let profiles = state
+        .settings
+        .profiles
+        .iter()
+        .map(|profile| Spans::from(format!("{} ({})", profile.name, profile.channel)))
+        .collect();
+    let active = state
+        .settings
+        .profiles
+        .iter()
+        .position(|x| x.name == state.settings.active_profile)
+        .unwrap_or(0);
+    let profiles = Tabs::new(profiles)
+        .block(Block::default().borders(Borders::ALL).title("Profiles"))
+        .select(active)
+        .highlight_style(
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        );
+    frame.render_widget(profiles, rows[2]);
+
+    // Download progress, favourite servers and play button
+    let bottom = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(
+            [
+                Constraint::Percentage(55),
+                Constraint::Percentage(30),
+                Constraint::Percentage(15),
+            ]
+            .as_ref(),
+        )
+        .split(rows[3]);
+    draw_progress(frame, launcher, bottom[0]);
+
+    let favourites: Vec<Span> = state
+        .settings
+        .active_profile()
+        .favourite_servers
+        .iter()
+        .take(9)
+        .enumerate()
+        .map(|(i, server)| Span::raw(format!("{}: {}  ", i + 1, server)))
+        .collect();
+    let favourites = Paragraph::new(Spans::from(favourites))
+        .block(Block::default().borders(Borders::ALL).title("Favourites"));
+    frame.render_widget(favourites, bottom[1]);
+
+    let play_style = if launcher.can_play() {
+        Style::default()
+            .fg(Color::Green)
+            .add_modifier(Modifier::BOLD)
+    } else {
+        Style::default().fg(Color::DarkGray)
+    };
+    let play = Paragraph::new(Span::styled(launcher.play_button_text(), play_style))
+        .alignment(Alignment::Center)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(play_style),
+        );
+    frame.render_widget(play, bottom[2]);
+
+    let footer = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Min(0), Constraint::Length(24)].as_ref())
+        .split(rows[4]);
+    frame.render_widget(
+        Paragraph::new(Span::styled(KEYS, Style::default().fg(Color::DarkGray))),
+        footer[0],
+    );
+    frame.render_widget(
+        Paragraph::new(launcher.rate_limit_text()).alignment(Alignment::Right),
+        footer[1],
+    );
+}
+
+fn draw_progress<B: Backend>(frame: &mut Frame<B>, launcher: &Launcher, area: Rect) {
+    let status_style = match launcher.state {
+        LauncherState::Error(_) => Style::default().fg(Color::Red),
+        _ => Style::default(),
+    };
+    let label = match &launcher.state {
+        LauncherState::Downloading(m) => {
+            let (downloaded, total) = m.download_progress();
+            let speed = m.download_speed() as u64;
+            let eta = match speed {
+                0 => "unknown".into(),
+                _ => HumanDuration(Duration::from_secs(
+                    total.saturating_sub(downloaded) / speed,
+                ))
+                .to_string(),
+            };
+            format!(
+                "{}/{} - {}/sec - {} left",
+                HumanBytes(downloaded),
+                HumanBytes(total),
+                HumanBytes(speed),
+                eta
+            )
+        }
+        LauncherState::Installing(m) => {
+            let (extracted, total) = m.bytes();
+            format!("{}/{}", HumanBytes(extracted), HumanBytes(total))
+        }
+        _ => String::new(),
+    };
+
+    let progress = Gauge::default()
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(Span::styled(launcher.status_text(), status_style)),
+        )
+        .gauge_style(Style::default().fg(Color::Green).bg(Color::Black))
+        .ratio((launcher.download_progress() as f64 / 100.0).min(1.0))
+        .label(label);
+    frame.render_widget(progress, area);
+}
+
+fn pane(title: &str, focused: bool) -> Block {
+    let style = if focused {
+        Style::default().fg(Color::Cyan)
+    } else {
+        Style::default()
+    };
+    Block::default()
+        .borders(Borders::ALL)
+        .border_style(style)
+        .title(title)
+}
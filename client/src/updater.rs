@@ -1,14 +1,14 @@
 use crate::Result;
-use crate::{filesystem, network};
+use crate::{filesystem, network, state::UpdatePolicy};
 use std::ffi::OsStr;
 
 // TODO: We should remove the installer after successful update!
 
-pub(crate) async fn update() -> Result<()> {
+pub(crate) async fn update(policy: UpdatePolicy) -> Result<()> {
     // Note: this will ignore network errors silently.
     if let Some(url) = network::check_win_update().await.ok().flatten() {
-        log::info!("Found airshipper update! It's highly recommended to update. Install? [Y/n]");
-        if crate::cli::confirm_action()? {
+        log::info!("Found airshipper update! It's highly recommended to update.");
+        if crate::cli::confirm_update(policy, "Install?")? {
             let mut resp = network::request(&url).await?;
             let path = filesystem::get_cache_path();
 
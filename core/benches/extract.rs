@@ -0,0 +1,112 @@
+//! Compares extracting a synthetic archive, laid out like the nightly builds, on one thread
+//! with extracting it on all cores.
+//!
+//! Run with `cargo bench -p airshipper-core --bench extract`.
+//! `ASSET_FILES` changes the amount of assets (default: 4000).
+
+use airshipper_core::{
+    filesystem,
+    network::{self, InstallProgress},
+};
+use std::{
+    io::{Cursor, Write},
+    path::Path,
+    time::Instant,
+};
+
+/// Size of the game executable
+const EXECUTABLE_SIZE: usize = 80 * 1024 * 1024;
+
+fn main() {
+    let files = std::env::var("ASSET_FILES")
+        .ok()
+        .and_then(|x| x.parse().ok())
+        .unwrap_or(4000);
+    let root = std::env::temp_dir().join(format!("airshipper-bench-{}", std::process::id()));
+    std::fs::create_dir_all(&root).unwrap();
+
+    let archive = root.join("veloren.zip");
+    let started = Instant::now();
+    let size = build(&archive, files);
+    println!(
+        "Created archive with {} assets: {} MiB compressed, {} MiB extracted ({:.1}s)",
+        files,
+        std::fs::metadata(&archive).unwrap().len() / 1024 / 1024,
+        size / 1024 / 1024,
+        started.elapsed().as_secs_f32()
+    );
+
+    let mut sequential = None;
+    for threads in &[1, network::default_threads()] {
+        let target = root.join(format!("extracted-{}", threads));
+        let started = Instant::now();
+        async_std::task::block_on(network::extract_zip(
+            &archive,
+            &target,
+            *threads,
+            &InstallProgress::default(),
+        ))
+        .unwrap();
+        let elapsed = started.elapsed().as_secs_f64();
+        let speedup = sequential.map(|x: f64| x / elapsed).unwrap_or(1.0);
+        sequential = sequential.or(Some(elapsed));
+        println!(
+            "{:>2} threads: {:.2}s ({:.0} MiB/s, {:.1}x)",
+            threads,
+            elapsed,
+            size as f64 / 1024.0 / 1024.0 / elapsed,
+            speedup
+        );
+        std::fs::remove_dir_all(&target).unwrap();
+    }
+
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+/// Writes the archive and returns its extracted size.
+fn build(path: &Path, files: usize) -> u64 {
+    let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let options = zip::write::FileOptions::default();
+    let mut size = 0;
+    let mut seed = 0x2545_f491u32;
+
+    // The first entry gets skipped by the installer
+    zip.add_directory("assets/", options).unwrap();
+    zip.start_file(filesystem::VOXYGEN_FILE, options).unwrap();
+    zip.write_all(&contents(EXECUTABLE_SIZE, &mut seed))
+        .unwrap();
+    size += EXECUTABLE_SIZE as u64;
+
+    for i in 0..files {
+        // Mostly small files with some large ones like the voxel models and sounds
+        let len = match i % 50 {
+            0 => 2 * 1024 * 1024,
+            x if x < 10 => 256 * 1024,
+            _ => 16 * 1024,
+        };
+        zip.start_file(format!("assets/{}/{}.vox", i % 40, i), options)
+            .unwrap();
+        zip.write_all(&contents(len, &mut seed)).unwrap();
+        size += len as u64;
+    }
+    std::fs::write(path, zip.finish().unwrap().into_inner()).unwrap();
+    size
+}
+
+/// Returns data which compresses about as well as the game files.
+fn contents(len: usize, seed: &mut u32) -> Vec<u8> {
+    (0..len)
+        .map(|i| {
+            // xorshift
+            *seed ^= *seed << 13;
+            *seed ^= *seed >> 17;
+            *seed ^= *seed << 5;
+            // Runs of equal bytes mixed with noise
+            if i % 4 == 0 {
+                *seed as u8
+            } else {
+                (i / 64) as u8
+            }
+        })
+        .collect()
+}
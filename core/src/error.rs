@@ -0,0 +1,196 @@
+//! Errors which can occur while managing the game
+
+use std::fmt;
+
+/// Everything which can go wrong while managing the game.
+#[derive(Debug)]
+pub enum ClientError {
+    /// Reading or writing files failed
+    IoError(std::io::Error),
+    /// The disk the path is located on has less bytes available (second) than needed (first)
+    NotEnoughSpace(std::path::PathBuf, u64, u64),
+    /// The request did not reach the server
+    NetworkError(isahc::Error),
+    /// The server at the url is unavailable at the moment
+    ServerError(String, isahc::http::StatusCode),
+    /// Any other error, the message is meant for the user
+    Custom(String),
+    // Should hopefully never occur
+    /// The news feed is invalid
+    RssError(rss::Error),
+    /// The downloaded build is corrupt
+    ZipError(zip::result::ZipError),
+    /// The downloaded build is corrupt
+    TarError(std::io::Error),
+    /// The logger has been set up already
+    LogError(log::SetLoggerError),
+    /// A path is not inside of the expected directory
+    StripPrefixError(std::path::StripPrefixError),
+    /// The request could not be built
+    HttpError(isahc::http::Error),
+    /// The settings could not be saved
+    SerializeError(ron::ser::Error),
+    /// The settings could not be loaded
+    DeserializeError(ron::de::Error),
+    /// An url is invalid
+    ParseError(url::ParseError),
+    /// Invalid command line arguments
+    UsageError(String),
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::IoError(x) => write!(f, "{}", x),
+            Self::NotEnoughSpace(path, required, available) => write!(
+                f,
+                "Not enough disk space at {}: {} MiB are needed but only {} MiB are available.",
+                path.display(),
+                mib(*required),
+                mib(*available)
+            ),
+            Self::NetworkError(x) => write!(
+                f,
+                "A network error occured ({}). Please check your internet connection and try again.",
+                x
+            ),
+            Self::ServerError(url, status) => write!(
+                f,
+                "{} is unavailable at the moment ({}). Please try again later.",
+                url, status
+            ),
+            Self::Custom(x) => write!(f, "{}", x),
+
+            Self::RssError(x) => write!(f, "Failed parsing news: {}", x),
+            Self::ZipError(x) => write!(f, "{}", x),
+            Self::TarError(x) => write!(f, "The downloaded build is corrupt: {}", x),
+            Self::StripPrefixError(x) => {
+                write!(f, "Failed to convert absolute to relative path: {}", x)
+            }
+            Self::LogError(_) => unreachable!(),
+            Self::SerializeError(x) => write!(f, "FATAL: Failed to save the settings! {}", x),
+            Self::DeserializeError(x) => write!(f, "FATAL: Failed to load the settings! {}", x),
+            Self::HttpError(x) => write!(f, "{}", x),
+            Self::ParseError(x) => write!(f, "{}", x),
+            Self::UsageError(x) => write!(f, "{}", x),
+        }
+    }
+}
+
+impl ClientError {
+    /// Exit code of airshipper if this error aborted it.
+    ///
+    /// These are part of the command line interface and must stay stable:
+    /// * `1` general error
+    /// * `2` invalid command line arguments
+    /// * `3` network error
+    /// * `4` filesystem error
+    /// * `5` corrupt download
+    /// * `6` settings could not be loaded or saved
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::Custom(_) | Self::RssError(_) | Self::LogError(_) | Self::ParseError(_) => 1,
+            Self::UsageError(_) => 2,
+            Self::NetworkError(_) | Self::ServerError(..) | Self::HttpError(_) => 3,
+            Self::IoError(_) | Self::NotEnoughSpace(..) | Self::StripPrefixError(_) => 4,
+            Self::ZipError(_) | Self::TarError(_) => 5,
+            Self::SerializeError(_) | Self::DeserializeError(_) => 6,
+        }
+    }
+
+    /// Whether sending the request again might succeed.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::NetworkError(e) => matches!(
+                e,
+                isahc::Error::Aborted
+                    | isahc::Error::ConnectFailed
+                    | isahc::Error::CouldntResolveHost
+                    | isahc::Error::Io(_)
+                    | isahc::Error::NoResponse
+                    | isahc::Error::ResponseBodyError(_)
+                    | isahc::Error::SSLConnectFailed(_)
+                    | isahc::Error::Timeout
+            ),
+            Self::ServerError(..) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Bytes rounded up to mebibytes, so a missing byte is not shown as zero.
+fn mib(bytes: u64) -> u64 {
+    (bytes + 1024 * 1024 - 1) / (1024 * 1024)
+}
+
+impl From<std::io::Error> for ClientError {
+    fn from(error: std::io::Error) -> Self {
+        Self::IoError(error)
+    }
+}
+
+impl From<isahc::Error> for ClientError {
+    fn from(error: isahc::Error) -> Self {
+        Self::NetworkError(error)
+    }
+}
+
+impl From<rss::Error> for ClientError {
+    fn from(error: rss::Error) -> Self {
+        Self::RssError(error)
+    }
+}
+
+impl From<isahc::http::Error> for ClientError {
+    fn from(error: isahc::http::Error) -> Self {
+        Self::HttpError(error)
+    }
+}
+
+impl From<log::SetLoggerError> for ClientError {
+    fn from(error: log::SetLoggerError) -> Self {
+        Self::LogError(error)
+    }
+}
+
+impl From<String> for ClientError {
+    fn from(error: String) -> Self {
+        Self::Custom(error)
+    }
+}
+
+impl From<&str> for ClientError {
+    fn from(error: &str) -> Self {
+        Self::Custom(error.into())
+    }
+}
+
+impl From<zip::result::ZipError> for ClientError {
+    fn from(error: zip::result::ZipError) -> Self {
+        Self::ZipError(error)
+    }
+}
+
+impl From<std::path::StripPrefixError> for ClientError {
+    fn from(error: std::path::StripPrefixError) -> Self {
+        Self::StripPrefixError(error)
+    }
+}
+
+impl From<ron::ser::Error> for ClientError {
+    fn from(error: ron::ser::Error) -> Self {
+        Self::SerializeError(error)
+    }
+}
+
+impl From<ron::de::Error> for ClientError {
+    fn from(error: ron::de::Error) -> Self {
+        Self::DeserializeError(error)
+    }
+}
+
+impl From<url::ParseError> for ClientError {
+    fn from(error: url::ParseError) -> Self {
+        Self::ParseError(error)
+    }
+}
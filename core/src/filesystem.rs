@@ -0,0 +1,470 @@
+//! Deals with all filesystem specific details
+
+use std::path::{Path, PathBuf};
+
+/// Name of the downloaded build
+#[cfg(windows)]
+pub const DOWNLOAD_FILE: &str = "veloren.zip";
+/// Name of the downloaded build
+#[cfg(unix)]
+pub const DOWNLOAD_FILE: &str = "veloren";
+
+/// Name of the game executable
+#[cfg(windows)]
+pub const VOXYGEN_FILE: &str = "veloren-voxygen.exe";
+/// Name of the game executable
+#[cfg(unix)]
+pub const VOXYGEN_FILE: &str = "veloren-voxygen";
+
+//#[cfg(windows)]
+//pub const SERVER_CLI_FILE: &str = "veloren-server-cli.exe";
+/// Name of the server executable
+#[cfg(unix)]
+pub const SERVER_CLI_FILE: &str = "veloren-server-cli";
+
+const SETTINGS_FILE: &str = "airshipper_settings.ron";
+const CACHE_FILE: &str = "airshipper_cache.ron";
+/// Mirror of each channel which answered last
+const MIRRORS_FILE: &str = "mirrors.ron";
+/// State file used before splitting it into settings and cache
+const LEGACY_STATE_FILE: &str = "airshipper_state.ron";
+const LOG_FILE: &str = "airshipper.log";
+const LOCK_FILE: &str = "airshipper.lock";
+/// Lock file inside of a profile which is held while the game is running or being installed
+pub const PROFILE_LOCK_FILE: &str = "profile.lock";
+/// Lock file next to the downloaded build which is held while it gets downloaded or installed
+pub const DOWNLOAD_LOCK_FILE: &str = "download.lock";
+/// Builds which can be streamed get extracted into this directory while downloading them
+const EXTRACT_DIR: &str = "extracted";
+/// Contains the version of a completely downloaded build which is ready to be installed
+const STAGED_FILE: &str = "staged_version";
+const DAEMON_LOCK_FILE: &str = "daemon.lock";
+const DAEMON_STATUS_FILE: &str = "daemon_status.ron";
+/// Tells other programs how to use the control interface
+const CONTROL_FILE: &str = "control.json";
+/// Placing this file next to the executable enables portable mode
+const PORTABLE_FILE: &str = "airshipper.portable";
+/// Environment variable to override where airshipper stores its data
+pub const ROOT_ENV: &str = "AIRSHIPPER_ROOT";
+
+lazy_static::lazy_static! {
+    // Base for profiles
+    static ref DATA_PATH: PathBuf = base();
+    // Base for the settings
+    static ref CONFIG_PATH: PathBuf = config();
+    // Base for cached remote data and partial downloads
+    static ref CACHE_PATH: PathBuf = cache();
+    // Base for logs and lock files
+    static ref STATE_PATH: PathBuf = state();
+    // Base for the assets
+    static ref ASSETS_PATH: PathBuf = assets();
+}
+
+/// Returns the base path where all airshipper files like config, profiles belong.
+/// On linux only the profiles are stored here, see [`xdg`].
+/// Can be overriden via [`custom_root`].
+///
+/// |Platform | Example                                                       |
+/// | ------- | ------------------------------------------------------------- |
+/// | Linux   | /home/alice/.local/share/barapp                               |
+/// | macOS   | /Users/Alice/Library/Application Support/com.Foo-Corp.Bar-App |
+/// | Windows | C:\Users\Alice\AppData\Roaming                                |
+fn base() -> PathBuf {
+    let path = custom_root().unwrap_or_else(|| {
+        dirs::data_dir()
+            .expect("Couldn't locate where to put launcher data!")
+            .join("airshipper")
+    });
+    std::fs::create_dir_all(&path).expect("failed to create data directory!");
+    path
+}
+
+/// Returns the path where the settings are stored.
+fn config() -> PathBuf {
+    create(xdg(dirs::config_dir()).unwrap_or_else(|| DATA_PATH.clone()))
+}
+
+/// Returns the path where cached data like the news and partial downloads are stored.
+fn cache() -> PathBuf {
+    create(xdg(dirs::cache_dir()).unwrap_or_else(|| DATA_PATH.join("cache")))
+}
+
+/// Returns the path where logs and lock files are stored.
+fn state() -> PathBuf {
+    // `XDG_STATE_HOME` is not supported by `dirs` yet.
+    let state_home = std::env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .filter(|x| x.is_absolute())
+        .or_else(|| dirs::home_dir().map(|x| x.join(".local").join("state")));
+    create(xdg(state_home).unwrap_or_else(|| DATA_PATH.clone()))
+}
+
+/// Follows the XDG base directory specification on linux unless a custom root is used.
+/// Returns `None` if everything should be kept together in the base path.
+#[cfg(target_os = "linux")]
+fn xdg(dir: Option<PathBuf>) -> Option<PathBuf> {
+    if custom_root().is_some() {
+        return None;
+    }
+    dir.map(|x| x.join("airshipper"))
+}
+
+/// Follows the XDG base directory specification on linux unless a custom root is used.
+/// Returns `None` if everything should be kept together in the base path.
+#[cfg(not(target_os = "linux"))]
+fn xdg(_: Option<PathBuf>) -> Option<PathBuf> {
+    None
+}
+
+fn create(path: PathBuf) -> PathBuf {
+    std::fs::create_dir_all(&path)
+        .unwrap_or_else(|e| panic!("failed to create {}: {}", path.display(), e));
+    path
+}
+
+/// Moves files which older versions kept in the base path to their new location.
+/// Returns the files which have been moved.
+pub fn migrate_layout() -> Vec<(PathBuf, PathBuf)> {
+    let targets = [
+        (SETTINGS_FILE, &*CONFIG_PATH),
+        (CACHE_FILE, &*CACHE_PATH),
+        (LOG_FILE, &*STATE_PATH),
+    ];
+
+    let mut moved = Vec::new();
+    let entries = match std::fs::read_dir(&*DATA_PATH) {
+        Ok(entries) => entries,
+        Err(_) => return moved,
+    };
+    for entry in entries.filter_map(|x| x.ok()) {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        // Including backups and such
+        let target = targets
+            .iter()
+            .find(|(file, _)| name.starts_with(file))
+            .map(|(_, dir)| dir.join(&*name));
+
+        match target {
+            Some(target) if target != entry.path() && !target.exists() => {
+                if move_file(&entry.path(), &target).is_ok() {
+                    moved.push((entry.path(), target));
+                }
+            }
+            _ => {}
+        }
+    }
+    moved
+}
+
+/// Returns the size of a file or of all files inside of a directory.
+pub fn size(path: &Path) -> u64 {
+    match std::fs::symlink_metadata(path) {
+        Ok(meta) if meta.is_dir() => std::fs::read_dir(path)
+            .map(|entries| {
+                entries
+                    .filter_map(|x| x.ok())
+                    .map(|x| size(&x.path()))
+                    .sum()
+            })
+            .unwrap_or(0),
+        Ok(meta) => meta.len(),
+        Err(_) => 0,
+    }
+}
+
+/// Removes a file or a directory with all of its contents (if it exists).
+pub fn remove(path: &Path) -> std::io::Result<()> {
+    let result = if path.is_dir() {
+        std::fs::remove_dir_all(path)
+    } else {
+        std::fs::remove_file(path)
+    };
+    match result {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Renames the file, falls back to copying for moves across filesystems.
+pub(crate) fn move_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    if std::fs::rename(from, to).is_err() {
+        std::fs::copy(from, to)?;
+        std::fs::remove_file(from)?;
+    }
+    Ok(())
+}
+
+/// Returns the location airshipper has been told to use instead of the default one.
+///
+/// In order of priority:
+/// 1. `AIRSHIPPER_ROOT` (also set by `--root`)
+/// 2. Next to the executable if the portable file exists (portable mode)
+fn custom_root() -> Option<PathBuf> {
+    if let Some(root) = std::env::var_os(ROOT_ENV).filter(|x| !x.is_empty()) {
+        let root = PathBuf::from(root);
+        // Profiles get started in their own directory, hence relative paths won't do.
+        return Some(match std::env::current_dir() {
+            Ok(cwd) if root.is_relative() => cwd.join(root),
+            _ => root,
+        });
+    }
+    portable_root()
+}
+
+/// Returns the directory of the executable if airshipper runs in portable mode.
+fn portable_root() -> Option<PathBuf> {
+    let mut path = std::env::current_exe().ok()?;
+    path.pop();
+    if path.join(PORTABLE_FILE).exists() {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+/// Whether airshipper keeps its data next to the executable.
+pub fn is_portable() -> bool {
+    std::env::var_os(ROOT_ENV)
+        .filter(|x| !x.is_empty())
+        .is_none()
+        && portable_root().is_some()
+}
+
+/// Tries to locate the static assets at various places.
+/// Priorities relative over absolute paths (e.g. next to the executable before checking /usr/share/airshipper/.. etc)
+fn assets() -> PathBuf {
+    find_assets().unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Returns where the assets are located or an error listing all searched folders.
+pub fn find_assets() -> Result<PathBuf, String> {
+    let mut paths = Vec::new();
+
+    // Executable path
+    if let Ok(mut path) = std::env::current_exe() {
+        path.pop();
+        paths.push(path);
+    }
+
+    // current working directory
+    if let Ok(path) = std::env::current_dir() {
+        paths.push(path);
+    }
+
+    // System paths
+    #[cfg(target_os = "linux")]
+    paths.push("/usr/share/airshipper/assets".into());
+
+    for path in paths.clone() {
+        match find_folder::Search::ParentsThenKids(3, 1)
+            .of(path)
+            .for_folder("assets")
+        {
+            Ok(assets_path) => return Ok(assets_path),
+            Err(_) => continue,
+        }
+    }
+
+    Err(format!(
+        "Airshipper assets could not be found! Searched folders:\n{})",
+        paths.iter().fold(String::new(), |mut a, path| {
+            a += &path.to_string_lossy();
+            a += "\n";
+            a
+        }),
+    ))
+}
+
+/// Returns the space available on the disk the path is located on.
+/// The path does not have to exist yet.
+pub fn free_space(path: &Path) -> std::io::Result<u64> {
+    let path = existing_ancestor(path);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = std::ffi::CString::new(path.as_os_str().as_bytes())?;
+        let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+        if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::ffi::OsStrExt;
+        use winapi::{shared::ntdef::ULARGE_INTEGER, um::fileapi::GetDiskFreeSpaceExW};
+
+        let path: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+        let mut free: ULARGE_INTEGER = unsafe { std::mem::zeroed() };
+        let result = unsafe {
+            GetDiskFreeSpaceExW(
+                path.as_ptr(),
+                &mut free,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+            )
+        };
+        if result == 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(unsafe { *free.QuadPart() })
+    }
+}
+
+/// Whether both paths are located on the same disk, hence files can be moved and linked
+/// between them without copying. The paths do not have to exist yet.
+pub fn same_disk(a: &Path, b: &Path) -> bool {
+    let (a, b) = (existing_ancestor(a), existing_ancestor(b));
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+
+        match (std::fs::metadata(a), std::fs::metadata(b)) {
+            (Ok(a), Ok(b)) => a.dev() == b.dev(),
+            _ => false,
+        }
+    }
+    #[cfg(windows)]
+    {
+        // The drive or network share
+        let root = |path: &Path| {
+            let path = path.canonicalize().ok()?;
+            let root = path.components().next()?;
+            Some(root.as_os_str().to_string_lossy().to_lowercase())
+        };
+        match (root(a), root(b)) {
+            (Some(a), Some(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+/// Only existing paths can be queried
+fn existing_ancestor(path: &Path) -> &Path {
+    path.ancestors()
+        .find(|x| x.exists())
+        .unwrap_or_else(|| Path::new("."))
+}
+
+/// Base path for profiles, for displaying it.
+pub fn data_path() -> impl std::fmt::Display {
+    DATA_PATH.display()
+}
+
+/// Path of the settings, for displaying it.
+pub fn config_path() -> impl std::fmt::Display {
+    CONFIG_PATH.display()
+}
+
+/// Path of logs and lock files, for displaying it.
+pub fn state_path() -> impl std::fmt::Display {
+    STATE_PATH.display()
+}
+
+/// Path of the assets, for displaying it.
+pub fn assets_path() -> impl std::fmt::Display {
+    ASSETS_PATH.display()
+}
+
+/// Returns path to where the profiles are stored.
+pub fn get_data_path() -> PathBuf {
+    DATA_PATH.clone()
+}
+
+/// Returns path to where cached data and temporary files like installers are stored.
+pub fn get_cache_path() -> PathBuf {
+    CACHE_PATH.clone()
+}
+
+/// Returns path to where all partial downloads are stored
+pub fn get_downloads_path() -> PathBuf {
+    CACHE_PATH.join("downloads")
+}
+
+/// Returns path to where the game of a profile gets downloaded to while creating the folder
+pub fn get_download_path(profile_name: &str) -> PathBuf {
+    create(get_downloads_path().join(profile_name)).join(DOWNLOAD_FILE)
+}
+
+/// Returns path to where the game of a profile gets extracted to while downloading it
+pub fn get_extract_path(profile_name: &str) -> PathBuf {
+    create(get_downloads_path().join(profile_name)).join(EXTRACT_DIR)
+}
+
+/// Returns path to the lock file which ensures only one download per profile is running
+pub fn get_download_lock_path(profile_name: &str) -> PathBuf {
+    create(get_downloads_path().join(profile_name)).join(DOWNLOAD_LOCK_FILE)
+}
+
+/// Returns path to the file which marks a download as staged
+pub fn get_staged_path(profile_name: &str) -> PathBuf {
+    get_downloads_path().join(profile_name).join(STAGED_FILE)
+}
+
+/// Returns path to the file which stores the user settings
+pub fn get_settings_path() -> PathBuf {
+    CONFIG_PATH.join(SETTINGS_FILE)
+}
+
+/// Returns path to the file which caches remote data like news
+pub fn get_cache_file_path() -> PathBuf {
+    CACHE_PATH.join(CACHE_FILE)
+}
+
+/// Returns path to where copies of remote text resources like the news feed are stored
+pub fn get_http_cache_path() -> PathBuf {
+    CACHE_PATH.join("http")
+}
+
+/// Returns path to the file which remembers the mirrors which answered during earlier runs
+pub fn get_mirrors_path() -> PathBuf {
+    CACHE_PATH.join(MIRRORS_FILE)
+}
+
+/// Returns path to the file which saved the state of older versions
+pub(crate) fn get_legacy_state_path() -> PathBuf {
+    DATA_PATH.join(LEGACY_STATE_FILE)
+}
+
+/// Returns path to where the assets are stored
+pub fn get_assets_path(name: &str) -> String {
+    ASSETS_PATH.join(name).display().to_string()
+}
+
+/// Returns path to a profile, the folder gets created once the game gets installed
+pub fn get_profile_path(profile_name: &str) -> std::path::PathBuf {
+    DATA_PATH.join("profiles").join(profile_name)
+}
+
+/// Returns path to the store which keeps the files of all installed builds
+pub fn get_store_path() -> PathBuf {
+    DATA_PATH.join("store")
+}
+
+/// Returns path to the lock file which ensures only one launcher is running
+pub fn get_lock_path() -> PathBuf {
+    STATE_PATH.join(LOCK_FILE)
+}
+
+/// Returns path to the lock file which ensures only one daemon is running
+pub fn get_daemon_lock_path() -> PathBuf {
+    STATE_PATH.join(DAEMON_LOCK_FILE)
+}
+
+/// Returns path to the file the daemon reports its status to
+pub fn get_daemon_status_path() -> PathBuf {
+    STATE_PATH.join(DAEMON_STATUS_FILE)
+}
+
+/// Returns path to the file containing address and token of the control interface
+pub fn get_control_path() -> PathBuf {
+    STATE_PATH.join(CONTROL_FILE)
+}
+
+/// Returns path to the file where the logs will be stored
+pub fn get_log_path() -> PathBuf {
+    STATE_PATH.join(LOG_FILE)
+}
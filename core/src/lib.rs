@@ -0,0 +1,45 @@
+//! Everything Airshipper needs to manage Veloren installations, without any user interface.
+//!
+//! The command line, the GUI and other front-ends are thin layers on top of this crate:
+//!
+//! * [`state::SavedState`] loads and saves the [`state::Settings`] (which contain the
+//!   [`profiles::Profile`]s) and the [`state::Cache`] of remote data like the news.
+//! * [`profiles::Profile`] checks for updates, downloads, installs and starts the game.
+//! * [`network`] and [`filesystem`] contain the lower level details, e.g. where files get stored.
+//! * [`store`] keeps a single copy of the files the installed builds have in common.
+//!
+//! ```no_run
+//! # async fn update() -> airshipper_core::Result<()> {
+//! use airshipper_core::state::SavedState;
+//!
+//! let mut state = SavedState::load().await?;
+//! let profile = state.settings.active_profile().clone();
+//! if profile.check_for_update().await? != profile.version {
+//!     let (_progress, download) = airshipper_core::network::download(
+//!         &profile,
+//!         None,
+//!         Default::default(),
+//!     )
+//!     .await?;
+//!     download.await?;
+//!     *state.settings.active_profile_mut() = profile.install().await?;
+//!     state.save().await?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+#![warn(missing_docs)]
+
+pub mod error;
+pub mod filesystem;
+pub mod lock;
+pub mod network;
+pub mod profiles;
+pub mod state;
+pub mod store;
+mod voxygen;
+
+pub use error::ClientError;
+
+/// Result type used throughout airshipper
+pub type Result<T> = std::result::Result<T, ClientError>;
@@ -0,0 +1,254 @@
+//! Lock files which keep multiple launchers (or a launcher and a running game)
+//! from working on the same files at the same time.
+//!
+//! The operating system locks the file (`flock` on unix, `LockFileEx` on windows) for as
+//! long as the handle stays open, hence locks of crashed processes get released right away.
+//! The file itself is never removed as another process might be waiting for that very file,
+//! it only contains the id of the owning process for information.
+
+use crate::Result;
+use std::{
+    fs::{File, OpenOptions},
+    io::{Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    process::{Child, Command},
+};
+
+/// Held lock which gets released once dropped.
+#[derive(Debug)]
+pub struct Lock {
+    path: PathBuf,
+    file: File,
+    /// Another process holds the lock too and keeps it after this one dropped it
+    handed_over: bool,
+}
+
+impl Lock {
+    /// Tries to acquire the lock for the current process.
+    /// Returns `None` if it is held by another running process.
+    pub fn acquire<P: Into<PathBuf>>(path: P) -> Result<Option<Self>> {
+        let path = path.into();
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .open(&path)?;
+        // `holder` takes a shared lock for a moment to find out whether it's held
+        let mut attempts = 0;
+        while !sys::try_lock(&file, true)? {
+            attempts += 1;
+            if attempts == 3 {
+                return Ok(None);
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+
+        let lock = Self {
+            path,
+            file,
+            handed_over: false,
+        };
+        lock.set_owner(Some(std::process::id()))?;
+        Ok(Some(lock))
+    }
+
+    /// Returns the id of the process holding the lock, `None` if it is free.
+    /// The id is `0` if the owner hasn't written it yet.
+    pub fn holder<P: AsRef<Path>>(path: P) -> Option<u32> {
+        let file = File::open(&path).ok()?;
+        // Shared locks can only be taken while nobody holds the exclusive one
+        if sys::try_lock(&file, false).ok()? {
+            return None;
+        }
+        let owner = std::fs::read_to_string(path).ok()?;
+        Some(owner.trim().parse().unwrap_or_default())
+    }
+
+    /// Starts the command with the lock being shared with the new process (e.g. the game),
+    /// so it stays held until both exited even if the launcher exits before it.
+    pub fn hand_over(mut self, command: &mut Command) -> Result<Child> {
+        sys::set_inheritable(&self.file, true)?;
+        let child = command.spawn();
+        sys::set_inheritable(&self.file, false)?;
+        let child = child?;
+
+        self.handed_over = true;
+        self.set_owner(Some(child.id()))?;
+        Ok(child)
+    }
+
+    fn set_owner(&self, pid: Option<u32>) -> Result<()> {
+        let mut file = &self.file;
+        file.set_len(0)?;
+        file.seek(SeekFrom::Start(0))?;
+        if let Some(pid) = pid {
+            write!(file, "{}", pid)?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Lock {
+    fn drop(&mut self) {
+        // The lock itself gets released by closing the file
+        if !self.handed_over {
+            if let Err(e) = self.set_owner(None) {
+                log::warn!("Failed to release lock {}: {}", self.path.display(), e);
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+mod sys {
+    use std::{fs::File, os::unix::io::AsRawFd};
+
+    /// Returns false if another handle holds a conflicting lock.
+    pub(super) fn try_lock(file: &File, exclusive: bool) -> std::io::Result<bool> {
+        let operation = if exclusive {
+            libc::LOCK_EX
+        } else {
+            libc::LOCK_SH
+        };
+        if unsafe { libc::flock(file.as_raw_fd(), operation | libc::LOCK_NB) } == 0 {
+            return Ok(true);
+        }
+        let error = std::io::Error::last_os_error();
+        match error.raw_os_error() {
+            Some(libc::EWOULDBLOCK) => Ok(false),
+            _ => Err(error),
+        }
+    }
+
+    /// Whether started processes get the handle (and with it the lock) too.
+    pub(super) fn set_inheritable(file: &File, inheritable: bool) -> std::io::Result<()> {
+        let fd = file.as_raw_fd();
+        unsafe {
+            let flags = libc::fcntl(fd, libc::F_GETFD);
+            if flags == -1 {
+                return Err(std::io::Error::last_os_error());
+            }
+            let flags = if inheritable {
+                flags & !libc::FD_CLOEXEC
+            } else {
+                flags | libc::FD_CLOEXEC
+            };
+            if libc::fcntl(fd, libc::F_SETFD, flags) == -1 {
+                return Err(std::io::Error::last_os_error());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+mod sys {
+    use std::{fs::File, os::windows::io::AsRawHandle};
+    use winapi::{
+        shared::winerror::ERROR_LOCK_VIOLATION,
+        um::{
+            fileapi::LockFileEx,
+            handleapi::SetHandleInformation,
+            minwinbase::{LOCKFILE_EXCLUSIVE_LOCK, LOCKFILE_FAIL_IMMEDIATELY, OVERLAPPED},
+            winbase::HANDLE_FLAG_INHERIT,
+        },
+    };
+
+    /// Locked bytes can't be read by others, hence a byte far behind the owner gets locked.
+    const LOCKED_OFFSET: u32 = 0x4000_0000;
+
+    /// Returns false if another handle holds a conflicting lock.
+    pub(super) fn try_lock(file: &File, exclusive: bool) -> std::io::Result<bool> {
+        let mut flags = LOCKFILE_FAIL_IMMEDIATELY;
+        if exclusive {
+            flags |= LOCKFILE_EXCLUSIVE_LOCK;
+        }
+        unsafe {
+            let mut overlapped: OVERLAPPED = std::mem::zeroed();
+            overlapped.u.s_mut().OffsetHigh = LOCKED_OFFSET;
+            if LockFileEx(file.as_raw_handle() as _, flags, 0, 1, 0, &mut overlapped) != 0 {
+                return Ok(true);
+            }
+        }
+        let error = std::io::Error::last_os_error();
+        match error.raw_os_error() {
+            Some(code) if code as u32 == ERROR_LOCK_VIOLATION => Ok(false),
+            _ => Err(error),
+        }
+    }
+
+    /// Whether started processes get the handle (and with it the lock) too.
+    pub(super) fn set_inheritable(file: &File, inheritable: bool) -> std::io::Result<()> {
+        let flags = if inheritable { HANDLE_FLAG_INHERIT } else { 0 };
+        let handle = file.as_raw_handle() as _;
+        if unsafe { SetHandleInformation(handle, HANDLE_FLAG_INHERIT, flags) } == 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lock_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "airshipper-lock-{}-{}.lock",
+            std::process::id(),
+            name
+        ));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn contention() {
+        let path = lock_path("contention");
+        let lock = Lock::acquire(&path).unwrap().expect("lock is free");
+        assert!(Lock::acquire(&path).unwrap().is_none());
+        assert_eq!(Lock::holder(&path), Some(std::process::id()));
+
+        drop(lock);
+        assert_eq!(Lock::holder(&path), None);
+        assert!(Lock::acquire(&path).unwrap().is_some());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn stale_lock() {
+        // Left behind by a crashed process, only the operating system's lock counts
+        for owner in &[
+            "",
+            "not a pid",
+            "4294967295",
+            &std::process::id().to_string(),
+        ] {
+            let path = lock_path("stale");
+            std::fs::write(&path, owner).unwrap();
+            assert_eq!(Lock::holder(&path), None);
+            let lock = Lock::acquire(&path).unwrap().expect("stale lock is free");
+            assert_eq!(Lock::holder(&path), Some(std::process::id()));
+            drop(lock);
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn hand_over() {
+        let path = lock_path("hand-over");
+        let lock = Lock::acquire(&path).unwrap().unwrap();
+        let mut child = lock
+            .hand_over(Command::new("sleep").arg("1"))
+            .expect("sleep can be started");
+
+        // Kept by the child after being dropped here
+        assert_eq!(Lock::holder(&path), Some(child.id()));
+        assert!(Lock::acquire(&path).unwrap().is_none());
+
+        child.wait().unwrap();
+        assert!(Lock::acquire(&path).unwrap().is_some());
+        std::fs::remove_file(path).unwrap();
+    }
+}
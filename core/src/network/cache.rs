@@ -0,0 +1,171 @@
+//! Keeps copies of remote text resources on disk and only downloads them again once they changed.
+//!
+//! Stored copies get revalidated with `If-None-Match`/`If-Modified-Since` and are
+//! used without asking the server at all as long as `Cache-Control: max-age` allows it.
+
+use super::{retry, send, settings, TIMEOUT};
+use crate::{filesystem, Result};
+use isahc::{
+    http::{header, HeaderMap, StatusCode},
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Stored copy of a resource
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    /// Unix timestamp until which the body can be used without revalidating it
+    fresh_until: i64,
+    body: String,
+}
+
+/// Returns the text behind the url, from disk if the stored copy is still up-to-date.
+/// Failed attempts get retried, see [`super::NetworkSettings::retries`].
+pub async fn fetch(url: &str) -> Result<String> {
+    retry::retry(|| fetch_once(url)).await
+}
+
+/// Like [`fetch`] but without retrying.
+pub(super) async fn fetch_once(url: &str) -> Result<String> {
+    let path = entry_path(url);
+    let cached = load(&path, url).await;
+    let now = chrono::Utc::now().timestamp();
+
+    let mut request = settings::get(url).timeout(TIMEOUT);
+    match &cached {
+        Some(entry) if now < entry.fresh_until => {
+            log::trace!("Using cached {}", url);
+            return Ok(entry.body.clone());
+        }
+        Some(entry) => {
+            if let Some(etag) = &entry.etag {
+                request = request.header(header::IF_NONE_MATCH, etag.as_str());
+            }
+            if let Some(last_modified) = &entry.last_modified {
+                request = request.header(header::IF_MODIFIED_SINCE, last_modified.as_str());
+            }
+        }
+        None => {}
+    }
+
+    let mut response = send(request.body(())?).await?;
+    let status = response.status();
+    let headers = response.headers();
+    let max_age = max_age(headers);
+    let etag = header_value(headers, header::ETAG);
+    let last_modified = header_value(headers, header::LAST_MODIFIED);
+
+    let entry = match cached {
+        Some(entry) if status == StatusCode::NOT_MODIFIED => {
+            log::trace!("{} has not been modified.", url);
+            // The validators may be left out if they did not change
+            Entry {
+                etag: etag.or(entry.etag),
+                last_modified: last_modified.or(entry.last_modified),
+                ..entry
+            }
+        }
+        _ if status.is_success() => Entry {
+            url: url.to_owned(),
+            etag,
+            last_modified,
+            fresh_until: 0,
+            body: response.text()?,
+        },
+        _ => {
+            return Err(format!("Couldn't download {}. Server returned: {}", url, status).into());
+        }
+    };
+
+    match max_age {
+        // Without validators or freshness the copy would never be used
+        Some(age) if age > 0 || entry.etag.is_some() || entry.last_modified.is_some() => {
+            let entry = Entry {
+                fresh_until: now + age,
+                ..entry
+            };
+            if let Err(e) = store(&path, &entry).await {
+                log::debug!("Failed to cache {}: {}", url, e);
+            }
+            Ok(entry.body)
+        }
+        _ => {
+            let _ = filesystem::remove(&path);
+            Ok(entry.body)
+        }
+    }
+}
+
+/// Returns the seconds the response may be used without revalidating it
+/// or `None` if it must not be stored at all.
+fn max_age(headers: &HeaderMap) -> Option<i64> {
+    let mut max_age = 0;
+    let directives = headers
+        .get_all(header::CACHE_CONTROL)
+        .iter()
+        .filter_map(|x| x.to_str().ok())
+        .flat_map(|x| x.split(','))
+        .map(|x| x.trim().to_ascii_lowercase());
+
+    for directive in directives {
+        if directive == "no-store" {
+            return None;
+        } else if directive == "no-cache" {
+            // Has to be revalidated every time
+            return Some(0);
+        } else if directive.starts_with("max-age=") {
+            max_age = directive["max-age=".len()..]
+                .trim_matches('"')
+                .parse()
+                .unwrap_or(0);
+        }
+    }
+    Some(max_age)
+}
+
+fn header_value(headers: &HeaderMap, name: header::HeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|x| x.to_str().ok())
+        .map(ToOwned::to_owned)
+}
+
+/// Returns the stored copy of the url, a corrupt one is treated as missing.
+async fn load(path: &Path, url: &str) -> Option<Entry> {
+    let contents = async_std::fs::read_to_string(path).await.ok()?;
+    match ron::de::from_str::<Entry>(&contents) {
+        // Different urls might end up with the same file name
+        Ok(entry) if entry.url == url => Some(entry),
+        Ok(_) => None,
+        Err(e) => {
+            log::debug!("Dropping corrupt cache entry of {}: {}", url, e);
+            None
+        }
+    }
+}
+
+async fn store(path: &Path, entry: &Entry) -> Result<()> {
+    let ron = ron::ser::to_string(entry)?;
+    async_std::fs::create_dir_all(filesystem::get_http_cache_path()).await?;
+    async_std::fs::write(path, ron).await?;
+    Ok(())
+}
+
+/// e.g. <cache>/http/https___veloren.net_rss.xml.ron
+fn entry_path(url: &str) -> PathBuf {
+    let name: String = url
+        .chars()
+        .map(|x| {
+            if x.is_ascii_alphanumeric() || x == '.' {
+                x
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    filesystem::get_http_cache_path().join(format!("{}.ron", name))
+}
@@ -0,0 +1,168 @@
+//! Extracts zip archives on the blocking thread pool, each task decompressing other entries.
+
+use crate::Result;
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+/// Progress of a running install. Clones share the same progress.
+#[derive(Debug, Clone, Default)]
+pub struct InstallProgress(Arc<InstallState>);
+
+#[derive(Debug, Default)]
+struct InstallState {
+    files: AtomicU64,
+    total_files: AtomicU64,
+    bytes: AtomicU64,
+    total_bytes: AtomicU64,
+    /// Last file which got extracted
+    current: Mutex<String>,
+    finished: AtomicBool,
+}
+
+impl InstallProgress {
+    /// Returns the extracted and the total amount of files.
+    pub fn files(&self) -> (u64, u64) {
+        (
+            self.0.files.load(Ordering::Relaxed),
+            self.0.total_files.load(Ordering::Relaxed),
+        )
+    }
+
+    /// Returns the extracted and the total amount of uncompressed bytes.
+    pub fn bytes(&self) -> (u64, u64) {
+        (
+            self.0.bytes.load(Ordering::Relaxed),
+            self.0.total_bytes.load(Ordering::Relaxed),
+        )
+    }
+
+    /// Returns the path of the file extracted last, relative to the profile.
+    pub fn current_file(&self) -> String {
+        self.0.current.lock().unwrap().clone()
+    }
+
+    /// Whether the install is done, successfully or not.
+    pub fn is_finished(&self) -> bool {
+        self.0.finished.load(Ordering::Relaxed)
+    }
+
+    /// Starts counting from zero, e.g. for the next step of the install.
+    pub(crate) fn start(&self, files: u64, bytes: u64) {
+        self.0.files.store(0, Ordering::Relaxed);
+        self.0.bytes.store(0, Ordering::Relaxed);
+        self.0.total_files.store(files, Ordering::Relaxed);
+        self.0.total_bytes.store(bytes, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add(&self, file: String, bytes: u64) {
+        self.0.files.fetch_add(1, Ordering::Relaxed);
+        self.0.bytes.fetch_add(bytes, Ordering::Relaxed);
+        *self.0.current.lock().unwrap() = file;
+    }
+
+    pub(super) fn finish(&self) {
+        self.0.finished.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Returns how many threads extract at the same time by default.
+pub fn default_threads() -> usize {
+    num_cpus::get().max(1)
+}
+
+/// Extracts all entries of the zip archive except the first one into `target`
+/// using `threads` threads, which keeps the async executor free meanwhile.
+pub async fn extract_zip(
+    archive: &Path,
+    target: &Path,
+    threads: usize,
+    progress: &InstallProgress,
+) -> Result<()> {
+    let (files, bytes) = contents(archive)?;
+    progress.start(files as u64, bytes);
+
+    // Every thread takes the next entry nobody is working on
+    let next = Arc::new(AtomicUsize::new(1));
+    let failed = Arc::new(AtomicBool::new(false));
+    let mut workers = Vec::new();
+    for _ in 0..threads.max(1).min(files.max(1)) {
+        let job = Job {
+            archive: archive.to_owned(),
+            target: target.to_owned(),
+            next: next.clone(),
+            failed: failed.clone(),
+            progress: progress.clone(),
+        };
+        workers.push(async_std::task::spawn_blocking(move || job.run()));
+    }
+
+    for result in futures::future::join_all(workers).await {
+        result?;
+    }
+    Ok(())
+}
+
+/// Returns the amount of files which get extracted and their uncompressed size.
+pub(super) fn contents(archive: &Path) -> Result<(usize, u64)> {
+    let mut zip = zip::ZipArchive::new(std::fs::File::open(archive)?)?;
+    let mut bytes = 0;
+    for i in 1..zip.len() {
+        bytes += zip.by_index(i)?.size();
+    }
+    Ok((zip.len().saturating_sub(1), bytes))
+}
+
+/// Work of a single extraction thread
+struct Job {
+    archive: PathBuf,
+    target: PathBuf,
+    next: Arc<AtomicUsize>,
+    /// Stops the others once one of them failed
+    failed: Arc<AtomicBool>,
+    progress: InstallProgress,
+}
+
+impl Job {
+    fn run(self) -> Result<()> {
+        let result = self.extract();
+        if result.is_err() {
+            self.failed.store(true, Ordering::Relaxed);
+        }
+        result
+    }
+
+    fn extract(&self) -> Result<()> {
+        // Entries can only be read one at a time, hence every thread opens its own
+        let mut zip = zip::ZipArchive::new(std::fs::File::open(&self.archive)?)?;
+        loop {
+            let i = self.next.fetch_add(1, Ordering::Relaxed);
+            if i >= zip.len() || self.failed.load(Ordering::Relaxed) {
+                return Ok(());
+            }
+
+            let mut file = zip.by_index(i)?;
+            let name = file.sanitized_name();
+            let path = self.target.join(&name);
+            if file.is_dir() {
+                std::fs::create_dir_all(path)?;
+            } else {
+                // The directory entry might come later or be missing
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                let mut target = std::fs::OpenOptions::new()
+                    .write(true)
+                    .create(true)
+                    .truncate(true)
+                    .open(path)?;
+                std::io::copy(&mut file, &mut target)?;
+            }
+            self.progress.add(name.display().to_string(), file.size());
+        }
+    }
+}
@@ -0,0 +1,648 @@
+//! Takes care of all network operations
+
+mod cache;
+mod extract;
+mod retry;
+mod segments;
+mod settings;
+mod stream;
+mod transport;
+
+pub use cache::fetch;
+pub use extract::{default_threads, extract_zip, InstallProgress};
+pub use retry::mirrors;
+pub use settings::{
+    configure, max_rate, proxy, set_max_rate, validate_proxy, DownloadWindow, NetworkSettings,
+};
+pub use transport::{set_transport, FakeTransport, HttpTransport, Transport};
+
+use crate::error::ClientError;
+use crate::filesystem;
+use crate::lock::Lock;
+use crate::profiles::{Channel, Profile};
+use crate::store;
+use crate::Result;
+use async_std::{
+    fs::File,
+    prelude::*,
+    task::{self, JoinHandle},
+};
+use isahc::{
+    http::{Request, StatusCode},
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+/// Serves the builds and their versions
+pub const DOWNLOAD_SERVER: &str = "https://download.veloren.net";
+/// Serves new versions of airshipper itself
+#[cfg(windows)]
+pub const UPDATE_SERVER: &str = "https://www.songtronix.com";
+
+const CHANGELOG_URL: &str = "https://gitlab.com/veloren/veloren/raw/master/CHANGELOG.md";
+const NEWS_URL: &str = "https://veloren.net/rss.xml";
+
+/// Requests taking longer than this fail
+const TIMEOUT: Duration = Duration::from_secs(20);
+/// Bytes read from the connection at once while downloading
+const BUFFER_SIZE: usize = 64 * 1024;
+/// Builds take up about this many times their download size once extracted
+const EXTRACTED_RATIO: u64 = 3;
+
+/// Use this method when making requests
+/// it will include required defaults to make secure https requests
+/// and goes through the configured proxy.
+/// Failed attempts get retried, see [`NetworkSettings::retries`].
+pub async fn request<T: ToString>(url: T) -> Result<Response<isahc::Body>> {
+    let url = &url.to_string();
+    retry::retry(move || async move {
+        let request = settings::get(url).timeout(TIMEOUT).body(())?;
+        send(request).await
+    })
+    .await
+}
+
+/// Sends the request via the transport.
+/// Answers meaning the server is unavailable at the moment count as failed requests.
+async fn send(request: Request<()>) -> Result<Response<isahc::Body>> {
+    let url = request.uri().to_string();
+    let response = transport::transport().send(request).await?;
+    let status = response.status();
+    if status.is_server_error()
+        || status == StatusCode::TOO_MANY_REQUESTS
+        || status == StatusCode::REQUEST_TIMEOUT
+    {
+        return Err(ClientError::ServerError(url, status));
+    }
+    Ok(response)
+}
+
+/// Returns the latest remote version of the channel
+pub async fn get_version(channel: Channel) -> Result<String> {
+    retry::mirrored(channel, move |mirror| async move {
+        cache::fetch_once(&get_version_uri(&mirror, channel)).await
+    })
+    .await
+}
+
+/// Returns the download url if a new version of airshipper has been released.
+#[cfg(windows)]
+pub async fn check_win_update() -> Result<Option<String>> {
+    use semver::Version;
+
+    let text = fetch(&format!("{}/download/latest", UPDATE_SERVER)).await?;
+    let lines = text.lines().take(2).collect::<Vec<&str>>();
+    let (version, url) = (
+        // Incase the remote version cannot be parsed we default to the current one.
+        Version::parse(lines[0].trim()).unwrap_or_else(|_| {
+            log::warn!("Ignoring corrupted remote version!");
+            Version::parse(env!("CARGO_PKG_VERSION")).unwrap()
+        }),
+        lines[1].trim(),
+    );
+
+    if version > Version::parse(env!("CARGO_PKG_VERSION")).unwrap() {
+        Ok(Some(url.into()))
+    } else {
+        Ok(None)
+    }
+}
+
+lazy_static::lazy_static! {
+    /// Aborts the download started via [`start_download`]
+    static ref CANCEL_DOWNLOAD: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
+}
+
+/// Aborts the running download started via [`start_download`].
+pub fn cancel_download() {
+    CANCEL_DOWNLOAD.store(true, Ordering::Relaxed);
+}
+
+/// Progress of a running download. Clones share the same progress.
+#[derive(Debug, Clone)]
+pub struct Progress(Arc<ProgressState>);
+
+#[derive(Debug)]
+struct ProgressState {
+    downloaded: AtomicU64,
+    total: AtomicU64,
+    started: Instant,
+    /// Limit of this download, the global one applies too
+    max_rate: Option<u64>,
+    /// Set once the download finished, contains the error message if it failed
+    outcome: Mutex<Option<std::result::Result<(), String>>>,
+}
+
+impl Progress {
+    fn new(total: u64, max_rate: Option<u64>) -> Self {
+        Self(Arc::new(ProgressState {
+            downloaded: AtomicU64::new(0),
+            total: AtomicU64::new(total),
+            started: Instant::now(),
+            max_rate,
+            outcome: Mutex::new(None),
+        }))
+    }
+
+    fn add(&self, bytes: u64) {
+        self.0.downloaded.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    fn finish(&self, result: &Result<()>) {
+        let outcome = result.as_ref().map(|_| ()).map_err(|e| e.to_string());
+        *self.0.outcome.lock().unwrap() = Some(outcome);
+    }
+
+    /// Returns the outcome once the download finished, `None` while it is running.
+    pub fn finished(&self) -> Option<Result<()>> {
+        self.0
+            .outcome
+            .lock()
+            .unwrap()
+            .clone()
+            .map(|x| x.map_err(Into::into))
+    }
+
+    /// Returns the downloaded and the total amount of bytes.
+    /// The total is zero if the server did not tell the size.
+    pub fn download_progress(&self) -> (u64, u64) {
+        (
+            self.0.downloaded.load(Ordering::Relaxed),
+            self.0.total.load(Ordering::Relaxed),
+        )
+    }
+
+    /// Returns the average speed in bytes per second.
+    pub fn download_speed(&self) -> f64 {
+        self.0.downloaded.load(Ordering::Relaxed) as f64 / self.total_time().as_secs_f64()
+    }
+
+    /// Returns how long the download has been running.
+    pub fn total_time(&self) -> Duration {
+        self.0.started.elapsed()
+    }
+
+    /// Returns the limit currently applied to the download in bytes per second.
+    pub fn rate_limit(&self) -> Option<u64> {
+        match (self.0.max_rate, max_rate()) {
+            (Some(x), Some(y)) => Some(x.min(y)),
+            (x, y) => x.or(y),
+        }
+    }
+}
+
+impl Default for Progress {
+    fn default() -> Self {
+        Self::new(0, None)
+    }
+}
+
+/// Keeps a download below a limit which might change while it is running.
+struct Throttle {
+    since: Instant,
+    bytes: u64,
+    rate: Option<u64>,
+}
+
+impl Throttle {
+    fn new() -> Self {
+        Self {
+            since: Instant::now(),
+            bytes: 0,
+            rate: None,
+        }
+    }
+
+    /// Pauses until the average speed drops below the limit.
+    async fn wait(&mut self, bytes: u64, rate: Option<u64>) {
+        if rate != self.rate {
+            // Measure from now on, the past speed doesn't matter for the new limit
+            self.since = Instant::now();
+            self.bytes = 0;
+            self.rate = rate;
+        }
+        self.bytes += bytes;
+
+        if let Some(rate) = rate {
+            let expected = Duration::from_secs_f64(self.bytes as f64 / rate as f64);
+            if let Some(wait) = expected.checked_sub(self.since.elapsed()) {
+                async_std::task::sleep(wait).await;
+            }
+        }
+    }
+}
+
+/// Starts a download of the zip to target directory
+pub async fn start_download(profile: &Profile) -> Result<Progress> {
+    log::info!("Downloading {} - {}", profile.name, profile.channel);
+
+    std::fs::create_dir_all(&profile.directory)?;
+    // Refuse to update while the game is running
+    drop(profile.lock()?);
+
+    CANCEL_DOWNLOAD.store(false, Ordering::Relaxed);
+    let (progress, task) = download(profile, None, CANCEL_DOWNLOAD.clone()).await?;
+    async_std::task::spawn(async move {
+        if let Err(e) = task.await {
+            log::error!("Download failed: {}", e);
+        }
+    });
+    Ok(progress)
+}
+
+/// Starts downloading the latest build of the profile. It is complete once the returned task finishes.
+/// The download is kept below `max_rate` bytes per second as well as the global limit
+/// (see [`set_max_rate`]) and aborted once `cancel` gets set.
+pub async fn download(
+    profile: &Profile,
+    max_rate: Option<u64>,
+    cancel: Arc<AtomicBool>,
+) -> Result<(Progress, JoinHandle<Result<()>>)> {
+    let lock = download_lock(&profile.name)?;
+    // The build is going to be overwritten
+    filesystem::remove(&filesystem::get_staged_path(&profile.name))?;
+    filesystem::remove(&filesystem::get_download_path(&profile.name))?;
+    let extract_path = filesystem::get_extract_path(&profile.name);
+    filesystem::remove(&extract_path)?;
+    filesystem::remove(&stream::hashes_path(&extract_path))?;
+
+    let channel = profile.channel;
+    let (url, response) = retry::mirrored(channel, move |mirror| async move {
+        let url = get_artifact_uri(&mirror, channel);
+        let response = send(settings::get(&url).body(())?).await?;
+        Ok((url, response))
+    })
+    .await?;
+    if !response.status().is_success() {
+        return Err(format!(
+            "Couldn't download the game. Server returned: {}",
+            response.status()
+        )
+        .into());
+    }
+
+    // Later requests must not end up at another build in case `latest` changed meanwhile
+    let url = response
+        .effective_uri()
+        .map(ToString::to_string)
+        .unwrap_or(url);
+    // Recorded with the build, a newer one might get released until it is installed
+    let version = get_version(channel).await?;
+    let staged_path = filesystem::get_staged_path(&profile.name);
+    let format = stream::Format::detect(&url, &response);
+    let total = content_length(&response);
+    if let Some(total) = total {
+        check_free_space(
+            &filesystem::get_downloads_path(),
+            required_space(format, total),
+        )?;
+    }
+    let progress = Progress::new(total.unwrap_or(0), max_rate);
+    let zip_path = filesystem::get_download_path(&profile.name);
+    let ranges = match total {
+        // Streams have to be read in order
+        Some(total) if !format.is_streamable() && segments::accepts_ranges(&response) => {
+            segments::split(total, settings::current().segments)
+        }
+        _ => Vec::new(),
+    };
+
+    let task_progress = progress.clone();
+    let task = async_std::task::spawn(async move {
+        let result = if format.is_streamable() {
+            log::debug!("Extracting the {:?} archive while downloading.", format);
+            stream::extract(response, format, &extract_path, &cancel, &task_progress).await
+        } else if ranges.len() > 1 {
+            log::debug!("Downloading in {} segments.", ranges.len());
+            let source = segments::Source::new(url, &response);
+            segments::save(
+                response,
+                &source,
+                &zip_path,
+                ranges,
+                &cancel,
+                &task_progress,
+            )
+            .await
+        } else {
+            save_body(response, &zip_path, &cancel, &task_progress).await
+        };
+        // The size wasn't known before, the archive still has to be extracted
+        let result = match result {
+            Ok(()) if total.is_none() && !format.is_streamable() => {
+                check_free_space(&zip_path, filesystem::size(&zip_path) * EXTRACTED_RATIO)
+            }
+            result => result,
+        };
+        let result = result.and_then(|()| Ok(std::fs::write(&staged_path, &version)?));
+        if result.is_err() {
+            // Never leave a partial download behind
+            let _ = std::fs::remove_file(&zip_path);
+        }
+        drop(lock);
+        task_progress.finish(&result);
+        result
+    });
+    Ok((progress, task))
+}
+
+/// Takes the lock which keeps others away from the downloaded build of the profile
+/// while it gets downloaded, installed or deleted.
+fn download_lock(profile_name: &str) -> Result<Lock> {
+    match Lock::acquire(filesystem::get_download_lock_path(profile_name))? {
+        Some(lock) => Ok(lock),
+        None => Err(format!(
+            "'{}' is being downloaded or installed at the moment.",
+            profile_name
+        )
+        .into()),
+    }
+}
+
+/// Deletes the downloaded build of the profile unless it is being downloaded or installed.
+/// The lock file stays as another process might be waiting for it. Returns the freed bytes.
+pub fn remove_download(profile_name: &str) -> Result<u64> {
+    let _lock = download_lock(profile_name)?;
+    let mut freed = 0;
+    for entry in std::fs::read_dir(filesystem::get_downloads_path().join(profile_name))? {
+        let path = entry?.path();
+        if path.file_name() != Some(filesystem::DOWNLOAD_LOCK_FILE.as_ref()) {
+            freed += filesystem::size(&path);
+            filesystem::remove(&path)?;
+        }
+    }
+    Ok(freed)
+}
+
+/// Returns the bytes needed to download and extract a build of the given size.
+fn required_space(format: stream::Format, size: u64) -> u64 {
+    match format {
+        // Extracted while downloading, the archive itself is never stored
+        stream::Format::Tar => size,
+        stream::Format::TarGz => size * EXTRACTED_RATIO,
+        stream::Format::Zip => size + size * EXTRACTED_RATIO,
+    }
+}
+
+/// Fails before writing anything if the disk of the path doesn't have `required` bytes left.
+fn check_free_space(path: &std::path::Path, required: u64) -> Result<()> {
+    match filesystem::free_space(path) {
+        Ok(available) if available < required => Err(ClientError::NotEnoughSpace(
+            path.to_owned(),
+            required,
+            available,
+        )),
+        Ok(_) => Ok(()),
+        // Better to try than to refuse
+        Err(e) => {
+            log::warn!(
+                "Failed to check the free space at {}: {}",
+                path.display(),
+                e
+            );
+            Ok(())
+        }
+    }
+}
+
+/// Returns the size of the body announced by the server.
+fn content_length<T>(response: &Response<T>) -> Option<u64> {
+    // Would be the compressed size
+    if response.headers().contains_key("Content-Encoding") {
+        return None;
+    }
+    response
+        .headers()
+        .get("Content-Length")?
+        .to_str()
+        .ok()?
+        .parse()
+        .ok()
+}
+
+/// Writes the body of the response into the file.
+async fn save_body(
+    mut response: Response<isahc::Body>,
+    path: &std::path::Path,
+    cancel: &AtomicBool,
+    progress: &Progress,
+) -> Result<()> {
+    let mut file = File::create(path).await?;
+    let downloaded = copy_body(
+        response.body_mut(),
+        &mut file,
+        u64::MAX,
+        1,
+        cancel,
+        progress,
+    )
+    .await?;
+    file.flush().await?;
+
+    // Servers closing the connection early don't always cause an error
+    let (_, total) = progress.download_progress();
+    if total != 0 && downloaded != total {
+        return Err(format!(
+            "Download incomplete: received {} of {} bytes.",
+            downloaded, total
+        )
+        .into());
+    }
+
+    log::info!("Download finished!");
+    Ok(())
+}
+
+/// Copies the body into the file until it ends or `limit` bytes have been written.
+/// The download limit gets shared by the `connections` running at the same time.
+/// Returns the amount of bytes written.
+async fn copy_body<W: async_std::io::Write + Unpin>(
+    body: &mut isahc::Body,
+    file: &mut W,
+    limit: u64,
+    connections: u64,
+    cancel: &AtomicBool,
+    progress: &Progress,
+) -> Result<u64> {
+    let mut buffer = vec![0; BUFFER_SIZE];
+    let mut written = 0;
+    let mut throttle = Throttle::new();
+
+    while written < limit {
+        if cancel.load(Ordering::Relaxed) {
+            return Err("Download cancelled.".into());
+        }
+        let max = (limit - written).min(BUFFER_SIZE as u64) as usize;
+        match body.read(&mut buffer[..max]).await? {
+            0 => break,
+            x => {
+                file.write_all(&buffer[..x]).await?;
+                written += x as u64;
+                progress.add(x as u64);
+                let rate = progress.rate_limit().map(|x| (x / connections).max(1));
+                throttle.wait(x as u64, rate).await;
+            }
+        }
+    }
+    Ok(written)
+}
+
+/// Returns the changes of the upcoming release.
+pub async fn query_changelog() -> Result<String> {
+    Ok(fetch(CHANGELOG_URL)
+        .await?
+        .lines()
+        .skip_while(|x| !x.contains(&"## [Unreleased]"))
+        .skip(2)
+        .take_while(|x| !x.contains(&"## [0.1.0]"))
+        .map(|x| format!("{}\n", x))
+        .collect())
+}
+
+/// Entry of the news feed
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Post {
+    /// Headline
+    pub title: String,
+    /// First lines of the post as plain text
+    pub description: String,
+    /// Link to the full post
+    pub button_url: String,
+}
+
+/// Returns a list of Posts with title, description and button url.
+pub async fn query_news() -> Result<Vec<Post>> {
+    let feed = rss::Channel::read_from(fetch(NEWS_URL).await?.as_bytes())?;
+    let mut posts = Vec::new();
+
+    for post in feed.items().iter().take(15) {
+        // Only take the latest posts
+        posts.push(Post {
+            title: post.title().unwrap_or("Missing title").into(),
+            description: process_description(post.description().unwrap_or("No description found.")),
+            button_url: post.link().unwrap_or("https://www.veloren.net").into(),
+        });
+    }
+
+    Ok(posts)
+}
+
+fn process_description(post: &str) -> String {
+    // TODO: Play with the width!
+    let stripped_html = html2text::from_read(post.as_bytes(), 400)
+        .lines()
+        .take(3)
+        .filter(|x| !x.contains("[banner]"))
+        .map(|x| format!("{}\n", x))
+        .collect::<String>();
+    let stripped_markdown = strip_markdown::strip_markdown(&stripped_html);
+    stripped_markdown
+}
+
+/// Moves the downloaded build into the store, links it into the profile and changes permissions.
+/// Returns the version of the installed build.
+pub async fn install(profile: &Profile, progress: &InstallProgress) -> Result<String> {
+    let result = install_build(profile, progress).await;
+    progress.finish();
+    result
+}
+
+async fn install_build(profile: &Profile, progress: &InstallProgress) -> Result<String> {
+    std::fs::create_dir_all(&profile.directory)?;
+    let _lock = profile.lock()?;
+    // Keeps downloads from replacing the build while it gets installed
+    let _download = download_lock(&profile.name)?;
+    let extract_path = filesystem::get_extract_path(&profile.name);
+    let zip_path = filesystem::get_download_path(&profile.name);
+    if !extract_path.exists() && !zip_path.exists() {
+        return Err("There is no downloaded build to install.".into());
+    }
+    let version = match profile.staged_version() {
+        Some(version) => version,
+        None => return Err("The downloaded build is incomplete, download it again.".into()),
+    };
+
+    let size = if extract_path.exists() {
+        filesystem::size(&extract_path)
+    } else {
+        extract::contents(&zip_path)?.1
+    };
+    // Moving the files into the store and linking them into the profile copies them
+    // if they are on another disk
+    let store_path = filesystem::get_store_path();
+    if !filesystem::same_disk(&extract_path, &store_path) {
+        check_free_space(&store_path, size)?;
+    }
+    if !filesystem::same_disk(&store_path, &profile.directory) {
+        check_free_space(&profile.directory, size)?;
+    }
+
+    if !extract_path.exists() {
+        // Extract next to the archive, the files get moved into the store afterwards
+        check_free_space(&extract_path, size)?;
+        let threads = default_threads();
+        log::info!("Unzipping {:?} using {} threads", zip_path, threads);
+        let partial = extract_path.with_extension("partial");
+        filesystem::remove(&partial)?;
+        if let Err(e) = extract_zip(&zip_path, &partial, threads, progress).await {
+            let _ = filesystem::remove(&partial);
+            return Err(e);
+        }
+        std::fs::rename(&partial, &extract_path)?;
+
+        // Delete downloaded zip
+        log::trace!("Extracted files, deleting zip archive.");
+        std::fs::remove_file(&zip_path)?;
+    }
+
+    log::info!("Linking extracted build into {:?}", profile.directory);
+    let (build, directory, name, hashes, task_progress) = (
+        extract_path.clone(),
+        profile.directory.clone(),
+        profile.name.clone(),
+        stream::hashes(&extract_path),
+        progress.clone(),
+    );
+    task::spawn_blocking(move || store::import(&build, &directory, &name, &hashes, &task_progress))
+        .await?;
+    filesystem::remove(&extract_path)?;
+    filesystem::remove(&stream::hashes_path(&extract_path))?;
+    filesystem::remove(&filesystem::get_staged_path(&profile.name))?;
+
+    // Files of the previous build might not be used anymore
+    if let Err(e) = task::spawn_blocking(store::collect_garbage).await {
+        log::warn!("Failed to clean up the store: {}", e);
+    }
+
+    #[cfg(unix)]
+    set_permissions(vec![
+        &profile.directory.join(filesystem::VOXYGEN_FILE),
+        &profile.directory.join(filesystem::SERVER_CLI_FILE),
+    ])?;
+
+    Ok(version)
+}
+
+fn get_version_uri(mirror: &str, channel: Channel) -> String {
+    format!("{}/version/{}/{}", mirror, std::env::consts::OS, channel)
+}
+fn get_artifact_uri(mirror: &str, channel: Channel) -> String {
+    format!("{}/latest/{}/{}", mirror, std::env::consts::OS, channel)
+}
+
+/// Tries to set executable permissions on linux
+#[cfg(unix)]
+fn set_permissions(files: Vec<&std::path::PathBuf>) -> Result<()> {
+    use std::process::Command;
+    for file in files {
+        Command::new("chmod").arg("+x").arg(file).spawn()?;
+    }
+    Ok(())
+}
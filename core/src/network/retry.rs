@@ -0,0 +1,134 @@
+//! Retries failed requests and fails over to other download mirrors.
+
+use super::{settings, DOWNLOAD_SERVER};
+use crate::{filesystem, profiles::Channel, Result};
+use rand::Rng;
+use std::{collections::HashMap, future::Future, sync::Mutex, time::Duration};
+
+/// Delay before the first retry, doubled for every following one
+const BACKOFF: Duration = Duration::from_millis(500);
+/// Longest delay between two attempts
+const MAX_BACKOFF: Duration = Duration::from_secs(8);
+
+lazy_static::lazy_static! {
+    /// Mirror of each channel which answered the last request, also during earlier runs
+    static ref HEALTHY: Mutex<HashMap<Channel, String>> = Mutex::new(load_healthy());
+}
+
+/// Returns the servers to try for the channel, starting with the one which worked last.
+pub fn mirrors(channel: Channel) -> Vec<String> {
+    let mut mirrors = settings::current()
+        .mirrors
+        .get(&channel)
+        .cloned()
+        .unwrap_or_default();
+    for mirror in &mut mirrors {
+        *mirror = mirror.trim_end_matches('/').to_owned();
+    }
+    if !mirrors.iter().any(|x| x == DOWNLOAD_SERVER) {
+        mirrors.push(DOWNLOAD_SERVER.to_owned());
+    }
+
+    if let Some(healthy) = HEALTHY.lock().unwrap().get(&channel) {
+        if let Some(idx) = mirrors.iter().position(|x| x == healthy) {
+            let healthy = mirrors.remove(idx);
+            mirrors.insert(0, healthy);
+        }
+    }
+    mirrors
+}
+
+/// Runs the request until it succeeds, fails permanently or the retries are used up.
+/// Only use it for requests which can safely be sent multiple times.
+pub(crate) async fn retry<T, F, Fut>(mut request: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let retries = settings::current().retries;
+    let mut attempt = 0;
+    loop {
+        match request().await {
+            Err(e) if e.is_transient() && attempt < retries => {
+                let delay = backoff(attempt);
+                log::warn!(
+                    "Request failed, retrying in {:.1}s ({}/{}): {}",
+                    delay.as_secs_f32(),
+                    attempt + 1,
+                    retries,
+                    e
+                );
+                async_std::task::sleep(delay).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Sends the request to the mirrors of the channel in turn until one of them answers.
+/// `request` gets the base url of the mirror, e.g. `https://download.veloren.net`.
+pub(crate) async fn mirrored<T, F, Fut>(channel: Channel, request: F) -> Result<T>
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut error = None;
+    for mirror in mirrors(channel) {
+        match retry(|| request(mirror.clone())).await {
+            Ok(x) => {
+                set_healthy(channel, mirror);
+                return Ok(x);
+            }
+            Err(e) if e.is_transient() => {
+                log::warn!("Mirror {} is unavailable: {}", mirror, e);
+                error = Some(e);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    Err(error.expect("official server is always a mirror!"))
+}
+
+/// Reads the mirrors which answered during earlier runs, a corrupt file is treated as missing.
+fn load_healthy() -> HashMap<Channel, String> {
+    let path = filesystem::get_mirrors_path();
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(_) => return HashMap::new(),
+    };
+    ron::de::from_str(&contents).unwrap_or_else(|e| {
+        log::debug!("Dropping corrupt {}: {}", path.display(), e);
+        HashMap::new()
+    })
+}
+
+/// Remembers the mirror for the following requests and runs.
+fn set_healthy(channel: Channel, mirror: String) {
+    let mut healthy = HEALTHY.lock().unwrap();
+    if healthy.get(&channel) == Some(&mirror) {
+        return;
+    }
+    healthy.insert(channel, mirror);
+    if let Err(e) = store_healthy(&healthy) {
+        log::debug!("Failed to remember the mirror of {}: {}", channel, e);
+    }
+}
+
+fn store_healthy(healthy: &HashMap<Channel, String>) -> Result<()> {
+    let ron = ron::ser::to_string(healthy)?;
+    std::fs::create_dir_all(filesystem::get_cache_path())?;
+    std::fs::write(filesystem::get_mirrors_path(), ron)?;
+    Ok(())
+}
+
+/// Returns a random delay which grows with every attempt, so clients
+/// which failed at the same time don't retry at the same time.
+fn backoff(attempt: u32) -> Duration {
+    let delay = BACKOFF
+        .checked_mul(2u32.saturating_pow(attempt))
+        .unwrap_or(MAX_BACKOFF)
+        .min(MAX_BACKOFF)
+        .as_millis() as u64;
+    Duration::from_millis(rand::thread_rng().gen_range(delay / 2, delay + 1))
+}
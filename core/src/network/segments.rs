@@ -0,0 +1,148 @@
+//! Downloads large files over several connections at once using `Range` requests.
+
+use super::{copy_body, retry, send, settings, Progress};
+use crate::Result;
+use async_std::{fs::OpenOptions, io::SeekFrom, prelude::*};
+use isahc::{
+    http::{header, Response, StatusCode},
+    Body,
+};
+use std::{ops::Range, path::Path, sync::atomic::AtomicBool};
+
+/// Files get split into segments of at least this many bytes
+const MIN_SEGMENT_SIZE: u64 = 1024 * 1024;
+
+/// Where the segments get requested from
+pub(super) struct Source {
+    url: String,
+    /// Makes sure all segments belong to the same file, see `If-Range`
+    validator: Option<String>,
+}
+
+impl Source {
+    pub(super) fn new(url: String, response: &Response<Body>) -> Self {
+        let validator = [header::ETAG, header::LAST_MODIFIED]
+            .iter()
+            .filter_map(|x| response.headers().get(x))
+            .filter_map(|x| x.to_str().ok())
+            // Weak etags must not be used for range requests
+            .find(|x| !x.starts_with("W/"))
+            .map(ToOwned::to_owned);
+        Self { url, validator }
+    }
+}
+
+/// Whether the server announced to answer `Range` requests.
+pub(super) fn accepts_ranges<T>(response: &Response<T>) -> bool {
+    response
+        .headers()
+        .get(header::ACCEPT_RANGES)
+        .and_then(|x| x.to_str().ok())
+        .map_or(false, |x| x.split(',').any(|x| x.trim() == "bytes"))
+}
+
+/// Splits the file into up to `segments` ranges of about the same size.
+pub(super) fn split(total: u64, segments: u32) -> Vec<Range<u64>> {
+    let segments = (total / MIN_SEGMENT_SIZE).min(segments as u64).max(1);
+    let size = (total + segments - 1) / segments;
+    (0..segments)
+        .map(|i| i * size..((i + 1) * size).min(total))
+        .collect()
+}
+
+/// Downloads all ranges at the same time into the file.
+/// The first range gets read from `first`, the response to the initial request.
+pub(super) async fn save(
+    first: Response<Body>,
+    source: &Source,
+    path: &Path,
+    ranges: Vec<Range<u64>>,
+    cancel: &AtomicBool,
+    progress: &Progress,
+) -> Result<()> {
+    let total = ranges.last().map_or(0, |x| x.end);
+    let file = async_std::fs::File::create(path).await?;
+    file.set_len(total).await?;
+    drop(file);
+
+    let connections = ranges.len() as u64;
+    let mut first = Some(first);
+    let segments = ranges.into_iter().map(|range| {
+        save_segment(
+            first.take(),
+            source,
+            path,
+            range,
+            connections,
+            cancel,
+            progress,
+        )
+    });
+    // The remaining segments get dropped as soon as one fails
+    futures::future::try_join_all(segments).await?;
+
+    log::info!("Download finished!");
+    Ok(())
+}
+
+async fn save_segment(
+    response: Option<Response<Body>>,
+    source: &Source,
+    path: &Path,
+    range: Range<u64>,
+    connections: u64,
+    cancel: &AtomicBool,
+    progress: &Progress,
+) -> Result<()> {
+    let mut response = match response {
+        Some(response) => response,
+        None => {
+            let range = &range;
+            retry::retry(move || async move { request(source, range).await }).await?
+        }
+    };
+
+    let mut file = OpenOptions::new().write(true).open(path).await?;
+    file.seek(SeekFrom::Start(range.start)).await?;
+    let len = range.end - range.start;
+    let written = copy_body(
+        response.body_mut(),
+        &mut file,
+        len,
+        connections,
+        cancel,
+        progress,
+    )
+    .await?;
+    file.flush().await?;
+
+    if written != len {
+        return Err(format!(
+            "Download incomplete: received {} of {} bytes starting at {}.",
+            written, len, range.start
+        )
+        .into());
+    }
+    Ok(())
+}
+
+async fn request(source: &Source, range: &Range<u64>) -> Result<Response<Body>> {
+    let mut request = settings::get(&source.url).header(
+        header::RANGE,
+        format!("bytes={}-{}", range.start, range.end - 1),
+    );
+    if let Some(validator) = &source.validator {
+        request = request.header(header::IF_RANGE, validator.as_str());
+    }
+
+    let response = send(request.body(())?).await?;
+    match response.status() {
+        StatusCode::PARTIAL_CONTENT => Ok(response),
+        // The whole file would be sent, e.g. because it changed meanwhile
+        status => Err(format!(
+            "Couldn't download a part of the game. Server returned: {}",
+            status
+        )
+        .into()),
+    }
+}
@@ -0,0 +1,334 @@
+//! Network related settings which apply to all requests
+
+use crate::{filesystem, profiles::Channel, Result};
+use isahc::{
+    config::CaCertificate,
+    http::{request::Builder, Uri},
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        RwLock,
+    },
+};
+
+/// Certificate bundles of the common distributions, the first one found gets extended
+#[cfg(unix)]
+const SYSTEM_CA_BUNDLES: &[&str] = &[
+    "/etc/ssl/certs/ca-certificates.crt",
+    "/etc/pki/tls/certs/ca-bundle.crt",
+    "/etc/ssl/ca-bundle.pem",
+    "/etc/pki/tls/cacert.pem",
+    "/etc/ssl/cert.pem",
+];
+/// Schannel only uses the given bundle instead of the system store
+#[cfg(windows)]
+const SYSTEM_CA_BUNDLES: &[&str] = &[];
+
+lazy_static::lazy_static! {
+    static ref SETTINGS: RwLock<NetworkSettings> = RwLock::new(NetworkSettings::default());
+    /// System certificates combined with the extra ones
+    static ref CA_BUNDLE: RwLock<Option<PathBuf>> = RwLock::new(None);
+}
+
+/// Download limit in bytes per second, zero if unlimited.
+/// Kept separately as it gets read for every chunk of the downloads.
+static MAX_RATE: AtomicU64 = AtomicU64::new(0);
+
+/// How requests get sent, retried and where builds get downloaded from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkSettings {
+    /// Attempts made after the first one failed
+    pub retries: u32,
+    /// Servers tried in order when downloading builds of a channel.
+    /// The official one gets tried last unless it's part of the list.
+    pub mirrors: HashMap<Channel, Vec<String>>,
+    /// e.g. `http://proxy:3128` or `socks5h://proxy:1080`.
+    /// Taken from the environment (`HTTPS_PROXY`, `ALL_PROXY`, ...) if not set.
+    pub proxy: Option<String>,
+    /// Certificate bundles (PEM) trusted in addition to the system ones
+    pub ca_certificates: Vec<PathBuf>,
+    /// Downloads are kept below this many bytes per second
+    pub max_rate: Option<u64>,
+    /// Time of the day during which the daemon may download builds, any time if not set
+    pub download_window: Option<DownloadWindow>,
+    /// Connections used at most to download a build, if the server supports it
+    pub segments: u32,
+}
+
+/// Time of the day (local time), e.g. `22:00-06:00`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DownloadWindow {
+    /// Minutes after midnight
+    pub start: u16,
+    /// Minutes after midnight, before `start` if the window spans midnight
+    pub end: u16,
+}
+
+impl DownloadWindow {
+    /// Whether the current time is part of the window.
+    pub fn is_open(self) -> bool {
+        use chrono::Timelike;
+
+        let now = chrono::Local::now();
+        self.contains((now.hour() * 60 + now.minute()) as u16)
+    }
+
+    /// Whether the minute after midnight is part of the window.
+    pub fn contains(self, minute: u16) -> bool {
+        if self.start <= self.end {
+            self.start <= minute && minute < self.end
+        } else {
+            minute >= self.start || minute < self.end
+        }
+    }
+}
+
+impl std::fmt::Display for DownloadWindow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:02}:{:02}-{:02}:{:02}",
+            self.start / 60,
+            self.start % 60,
+            self.end / 60,
+            self.end % 60
+        )
+    }
+}
+
+impl std::str::FromStr for DownloadWindow {
+    type Err = crate::error::ClientError;
+
+    fn from_str(window: &str) -> Result<Self> {
+        let minutes = |time: &str| -> Option<u16> {
+            let mut parts = time.trim().splitn(2, ':');
+            let hours: u16 = parts.next()?.parse().ok()?;
+            let minutes: u16 = parts.next().unwrap_or("0").parse().ok()?;
+            Some(hours * 60 + minutes).filter(|_| hours < 24 && minutes < 60)
+        };
+
+        let mut times = window.splitn(2, '-');
+        match (
+            times.next().and_then(minutes),
+            times.next().and_then(minutes),
+        ) {
+            (Some(start), Some(end)) if start != end => Ok(Self { start, end }),
+            _ => Err(format!(
+                "Invalid download window '{}'. Expected e.g. 22:00-06:00",
+                window
+            )
+            .into()),
+        }
+    }
+}
+
+impl Default for NetworkSettings {
+    fn default() -> Self {
+        Self {
+            retries: 3,
+            mirrors: HashMap::new(),
+            proxy: None,
+            ca_certificates: Vec::new(),
+            max_rate: None,
+            download_window: None,
+            segments: 4,
+        }
+    }
+}
+
+/// Applies the settings to all following requests.
+/// The certificate bundle only gets rebuilt if the certificates changed.
+pub fn configure(settings: &NetworkSettings) {
+    let changed = settings.ca_certificates != SETTINGS.read().unwrap().ca_certificates;
+    let mut bundle = CA_BUNDLE.write().unwrap();
+    if settings.ca_certificates.is_empty() {
+        *bundle = None;
+    } else if changed || bundle.is_none() {
+        *bundle = build_ca_bundle(&settings.ca_certificates)
+            .map_err(|e| log::error!("Failed to add the extra certificates: {}", e))
+            .ok();
+    }
+    drop(bundle);
+    set_max_rate(settings.max_rate);
+    *SETTINGS.write().unwrap() = settings.clone();
+}
+
+/// Changes the download limit (bytes per second), applies to running downloads too.
+pub fn set_max_rate(max_rate: Option<u64>) {
+    MAX_RATE.store(max_rate.unwrap_or(0), Ordering::Relaxed);
+}
+
+/// Returns the download limit in bytes per second.
+pub fn max_rate() -> Option<u64> {
+    Some(MAX_RATE.load(Ordering::Relaxed)).filter(|x| *x > 0)
+}
+
+/// Returns the settings applied to the requests.
+pub(crate) fn current() -> NetworkSettings {
+    SETTINGS.read().unwrap().clone()
+}
+
+/// Returns the proxy requests to the url are sent through, `None` if they go out directly.
+pub fn proxy(url: &str) -> Option<String> {
+    if let Some(proxy) = SETTINGS.read().unwrap().proxy.clone() {
+        return Some(proxy);
+    }
+
+    let scheme = if url.starts_with("https:") {
+        "https_proxy"
+    } else {
+        "http_proxy"
+    };
+    [scheme, "all_proxy"]
+        .iter()
+        .flat_map(|x| vec![x.to_uppercase(), x.to_string()])
+        .filter_map(|x| std::env::var(x).ok())
+        .find(|x| !x.trim().is_empty())
+}
+
+/// Whether the proxy url can be used.
+pub fn validate_proxy(proxy: &str) -> Result<()> {
+    let uri = proxy
+        .parse::<Uri>()
+        .map_err(|e| format!("Invalid proxy '{}': {}", proxy, e))?;
+    match uri.scheme_str() {
+        Some("http") | Some("https") | Some("socks5") | Some("socks5h") => Ok(()),
+        _ => Err(format!(
+            "Invalid proxy '{}'. Supported are http://, https://, socks5:// and socks5h://",
+            proxy
+        )
+        .into()),
+    }
+}
+
+/// Starts a GET request with the proxy and certificates applied.
+pub(crate) fn get(url: &str) -> Builder {
+    let mut request = isahc::http::Request::get(url);
+
+    if let Some(proxy) = proxy(url) {
+        match proxy.parse::<Uri>() {
+            Ok(proxy) => request = request.proxy(proxy),
+            Err(e) => log::warn!("Ignoring invalid proxy '{}': {}", proxy, e),
+        }
+        // Set explicitly as the proxy might not come from the environment
+        if let Some(hosts) = ["NO_PROXY", "no_proxy"]
+            .iter()
+            .find_map(|x| std::env::var(x).ok())
+        {
+            request = request.proxy_blacklist(
+                hosts
+                    .split(',')
+                    .map(|x| x.trim().to_owned())
+                    .filter(|x| !x.is_empty())
+                    .collect::<Vec<_>>(),
+            );
+        }
+    }
+
+    if let Some(bundle) = &*CA_BUNDLE.read().unwrap() {
+        request = request.ssl_ca_certificate(CaCertificate::file(bundle));
+    }
+    request
+}
+
+/// Writes the system certificates followed by the extra ones into a single bundle
+/// as only one of them can be passed to curl. The bundle gets replaced at once as
+/// requests of other processes might be reading it.
+fn build_ca_bundle(certificates: &[PathBuf]) -> Result<PathBuf> {
+    let system = std::env::var_os("SSL_CERT_FILE")
+        .map(PathBuf::from)
+        .into_iter()
+        .chain(SYSTEM_CA_BUNDLES.iter().map(PathBuf::from))
+        .find(|x| x.is_file());
+
+    let path = filesystem::get_cache_path().join("ca-bundle.pem");
+    std::fs::create_dir_all(filesystem::get_cache_path())?;
+    let mut contents = Vec::new();
+    for certificate in system.iter().chain(certificates) {
+        let certificate =
+            std::fs::read(certificate).map_err(|e| format!("{}: {}", certificate.display(), e))?;
+        contents.extend_from_slice(&certificate);
+        contents.push(b'\n');
+    }
+    let partial = path.with_extension(format!("{}.partial", std::process::id()));
+    std::fs::write(&partial, contents)?;
+    if let Err(e) = std::fs::rename(&partial, &path) {
+        let _ = std::fs::remove_file(&partial);
+        return Err(e.into());
+    }
+    log::debug!(
+        "Trusting {} in addition to {}",
+        certificates
+            .iter()
+            .map(|x| x.display().to_string())
+            .collect::<Vec<_>>()
+            .join(", "),
+        system
+            .as_ref()
+            .map(|x| x.display().to_string())
+            .unwrap_or_else(|| "no system certificates".into())
+    );
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(window: &str) -> (u16, u16) {
+        let window: DownloadWindow = window.parse().unwrap();
+        (window.start, window.end)
+    }
+
+    #[test]
+    fn download_window() {
+        assert_eq!(window("08:30-12:15"), (510, 735));
+        assert_eq!(window(" 9 - 17 "), (540, 1020));
+        assert_eq!(window("00:00-23:59"), (0, 1439));
+        assert_eq!(
+            "8:05-9:00".parse::<DownloadWindow>().unwrap().to_string(),
+            "08:05-09:00"
+        );
+
+        let window: DownloadWindow = "08:30-12:15".parse().unwrap();
+        assert!(window.contains(510));
+        assert!(window.contains(734));
+        assert!(!window.contains(735));
+        assert!(!window.contains(0));
+    }
+
+    #[test]
+    fn download_window_over_midnight() {
+        let window: DownloadWindow = "22:00-06:00".parse().unwrap();
+        assert_eq!((window.start, window.end), (1320, 360));
+        assert!(window.contains(1320));
+        assert!(window.contains(1439));
+        assert!(window.contains(0));
+        assert!(window.contains(359));
+        assert!(!window.contains(360));
+        assert!(!window.contains(12 * 60));
+        assert!(!window.contains(1319));
+    }
+
+    #[test]
+    fn invalid_download_window() {
+        for window in &[
+            "",
+            "22:00",
+            "22:00-",
+            "24:00-06:00",
+            "22:60-06:00",
+            "10:00-10:00",
+            "ten-eleven",
+            "22:00-06:00-08:00",
+        ] {
+            assert!(window.parse::<DownloadWindow>().is_err(), "{}", window);
+        }
+    }
+}
@@ -0,0 +1,292 @@
+//! Extracts builds which can be read as a stream (tar) while they are being downloaded.
+//!
+//! The body gets passed in chunks to a blocking task unpacking it into a partial directory
+//! which only replaces the staged build once both download and extraction succeeded.
+//! The files get hashed while unpacking them, which saves reading them again when
+//! moving them into the store (see [`hashes`]).
+
+use super::{content_length, copy_body, Progress};
+use crate::{error::ClientError, filesystem, store, Result};
+use futures::{channel::mpsc, executor::BlockingStream, io::AsyncWrite, Sink};
+use isahc::{
+    http::{header, Response},
+    Body,
+};
+use sha2::{Digest, Sha256};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    io::Read,
+    path::{Path, PathBuf},
+    pin::Pin,
+    rc::Rc,
+    sync::atomic::AtomicBool,
+    task::{Context, Poll},
+};
+use tar::EntryType;
+
+/// Chunks waiting to be extracted, limits how far the download can get ahead
+const QUEUED_CHUNKS: usize = 64;
+
+/// Archive formats of the builds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum Format {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl Format {
+    /// Guesses the format from the file name (url or `Content-Disposition`) and the content type.
+    pub(super) fn detect<T>(url: &str, response: &Response<T>) -> Self {
+        let header = |name: header::HeaderName| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|x| x.to_str().ok())
+                .map(|x| x.to_ascii_lowercase())
+        };
+        let path = url.split(|x| x == '?' || x == '#').next().unwrap_or(url);
+        let names = std::iter::once(path.to_ascii_lowercase()).chain(
+            header(header::CONTENT_DISPOSITION)
+                .map(|x| x.trim_end_matches(|x| x == '"' || x == '\'').to_owned()),
+        );
+        for name in names {
+            if name.ends_with(".tar") {
+                return Self::Tar;
+            } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+                return Self::TarGz;
+            }
+        }
+
+        match header(header::CONTENT_TYPE).as_deref() {
+            Some("application/x-tar") => Self::Tar,
+            Some("application/gzip") | Some("application/x-gzip") | Some("application/x-gtar") => {
+                Self::TarGz
+            }
+            _ => Self::Zip,
+        }
+    }
+
+    /// Whether the archive can be extracted while downloading it.
+    pub(super) fn is_streamable(self) -> bool {
+        self != Self::Zip
+    }
+}
+
+/// Downloads the build and extracts it into `path` at the same time.
+pub(super) async fn extract(
+    mut response: Response<Body>,
+    format: Format,
+    path: &Path,
+    cancel: &AtomicBool,
+    progress: &Progress,
+) -> Result<()> {
+    let partial = path.with_extension("partial");
+    filesystem::remove(&partial)?;
+    filesystem::remove(path)?;
+    filesystem::remove(&hashes_path(path))?;
+
+    let (sender, receiver) = mpsc::channel(QUEUED_CHUNKS);
+    let target = partial.clone();
+    let unpacked = async_std::task::spawn_blocking(move || {
+        let reader = ChunkReader {
+            chunks: futures::executor::block_on_stream(receiver),
+            chunk: Vec::new(),
+            position: 0,
+        };
+        unpack(format, reader, &target)
+    });
+
+    let mut writer = ChunkWriter(sender);
+    let downloaded = copy_body(
+        response.body_mut(),
+        &mut writer,
+        u64::MAX,
+        1,
+        cancel,
+        progress,
+    )
+    .await;
+    // Signals the end of the archive
+    drop(writer);
+    let unpacked = unpacked.await;
+
+    let result = match (downloaded, unpacked) {
+        (Ok(downloaded), Ok(hashes)) => match content_length(&response) {
+            // Servers closing the connection early don't always cause an error
+            Some(total) if downloaded != total => Err(format!(
+                "Download incomplete: received {} of {} bytes.",
+                downloaded, total
+            )
+            .into()),
+            _ => Ok(hashes),
+        },
+        // The extraction stopped reading, hence it knows why
+        (Err(ClientError::IoError(e)), Err(unpacked))
+            if e.kind() == std::io::ErrorKind::BrokenPipe =>
+        {
+            Err(unpacked)
+        }
+        (Err(e), _) | (Ok(_), Err(e)) => Err(e),
+    };
+
+    match result {
+        Ok(hashes) => {
+            let hashes = ron::ser::to_string(&hashes)?;
+            std::fs::write(hashes_path(path), hashes)?;
+            std::fs::rename(&partial, path)?;
+            log::info!("Download and extraction finished!");
+            Ok(())
+        }
+        Err(e) => {
+            // Never leave a partial build behind
+            let _ = filesystem::remove(&partial);
+            Err(e)
+        }
+    }
+}
+
+/// Returns the hashes of the files of the build extracted to `path` by [`extract`],
+/// none if it has been extracted differently.
+pub(super) fn hashes(path: &Path) -> HashMap<String, String> {
+    let contents = match std::fs::read_to_string(hashes_path(path)) {
+        Ok(contents) => contents,
+        Err(_) => return HashMap::new(),
+    };
+    ron::de::from_str(&contents).unwrap_or_else(|e| {
+        log::debug!("Hashing the extracted files again: {}", e);
+        HashMap::new()
+    })
+}
+
+/// e.g. <downloads>/default/extracted.hashes
+pub(super) fn hashes_path(path: &Path) -> PathBuf {
+    path.with_extension("hashes")
+}
+
+/// Unpacks the archive, runs as a blocking task as reading blocks.
+/// Returns the hashes of the unpacked files by their name in the store.
+fn unpack(format: Format, reader: ChunkReader, target: &Path) -> Result<HashMap<String, String>> {
+    std::fs::create_dir_all(target)?;
+    let reader: Box<dyn Read> = match format {
+        Format::TarGz => Box::new(flate2::read::GzDecoder::new(reader)),
+        _ => Box::new(reader),
+    };
+    let hasher = Rc::new(RefCell::new(None));
+    let mut archive = tar::Archive::new(HashingReader {
+        inner: reader,
+        hasher: hasher.clone(),
+    });
+
+    let mut hashes = HashMap::new();
+    // Like `Archive::unpack` the directories come last as they might be read-only
+    let mut directories = Vec::new();
+    for entry in archive.entries().map_err(ClientError::TarError)? {
+        let mut entry = entry.map_err(ClientError::TarError)?;
+        match entry.header().entry_type() {
+            EntryType::Directory => {
+                directories.push(entry);
+                continue;
+            }
+            // Only the contents of regular files get read while unpacking them
+            EntryType::Regular => *hasher.borrow_mut() = Some(Sha256::new()),
+            _ => {}
+        }
+        let unpacked = entry.unpack_in(target).map_err(ClientError::TarError)?;
+        if let Some(hasher) = hasher.borrow_mut().take() {
+            if unpacked {
+                let path = entry.path().map_err(ClientError::TarError)?;
+                hashes.insert(store::name(&path), store::hex(&hasher.result()));
+            }
+        }
+    }
+    for mut directory in directories {
+        directory.unpack_in(target).map_err(ClientError::TarError)?;
+    }
+
+    // The padding after the archive has to be received too
+    std::io::copy(&mut archive.into_inner(), &mut std::io::sink())?;
+    Ok(hashes)
+}
+
+/// Hashes the bytes read while `hasher` is set, e.g. the contents of the current file.
+struct HashingReader<R> {
+    inner: R,
+    hasher: Rc<RefCell<Option<Sha256>>>,
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = self.inner.read(buf)?;
+        if let Some(hasher) = &mut *self.hasher.borrow_mut() {
+            hasher.input(&buf[..len]);
+        }
+        Ok(len)
+    }
+}
+
+/// Passes the written bytes on to the extraction.
+struct ChunkWriter(mpsc::Sender<Vec<u8>>);
+
+impl AsyncWrite for ChunkWriter {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let sender = Pin::new(&mut self.0);
+        match sender.poll_ready(cx) {
+            Poll::Ready(Ok(())) => {}
+            Poll::Ready(Err(_)) => return Poll::Ready(Err(broken_pipe())),
+            Poll::Pending => return Poll::Pending,
+        }
+        match Pin::new(&mut self.0).start_send(buf.to_vec()) {
+            Ok(()) => Poll::Ready(Ok(buf.len())),
+            Err(_) => Poll::Ready(Err(broken_pipe())),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        // Chunks are sent right away
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        self.0.close_channel();
+        Poll::Ready(Ok(()))
+    }
+}
+
+fn broken_pipe() -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::BrokenPipe,
+        "The extraction stopped reading.",
+    )
+}
+
+/// Reads the chunks received from the download, blocks until the next one arrived.
+struct ChunkReader {
+    chunks: BlockingStream<mpsc::Receiver<Vec<u8>>>,
+    chunk: Vec<u8>,
+    position: usize,
+}
+
+impl Read for ChunkReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.position == self.chunk.len() {
+            match self.chunks.next() {
+                Some(chunk) => {
+                    self.chunk = chunk;
+                    self.position = 0;
+                }
+                // The download finished or failed
+                None => return Ok(0),
+            }
+        }
+        let len = buf.len().min(self.chunk.len() - self.position);
+        buf[..len].copy_from_slice(&self.chunk[self.position..self.position + len]);
+        self.position += len;
+        Ok(len)
+    }
+}
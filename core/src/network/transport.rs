@@ -0,0 +1,131 @@
+//! Sends the requests. Swap it out via [`set_transport`] to run without network access.
+
+use crate::Result;
+use futures::future::BoxFuture;
+use isahc::{
+    config::RedirectPolicy,
+    http::{Request, Response, StatusCode},
+    prelude::*,
+    Body, HttpClient,
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, RwLock},
+};
+
+/// Redirects followed before giving up, protects against redirect loops
+const MAX_REDIRECTS: u32 = 10;
+
+lazy_static::lazy_static! {
+    static ref TRANSPORT: RwLock<Arc<dyn Transport>> = RwLock::new(Arc::new(HttpTransport::new()));
+}
+
+/// Everything network related goes through this.
+pub trait Transport: Send + Sync {
+    /// Sends the request and returns the response once its headers arrived.
+    /// The body gets streamed while reading it.
+    fn send(&self, request: Request<()>) -> BoxFuture<'_, Result<Response<Body>>>;
+}
+
+/// Replaces the transport used by all requests, e.g. with a [`FakeTransport`] in tests.
+pub fn set_transport<T: Transport + 'static>(transport: T) {
+    *TRANSPORT.write().unwrap() = Arc::new(transport);
+}
+
+/// Returns the transport all requests have to use.
+pub(crate) fn transport() -> Arc<dyn Transport> {
+    TRANSPORT.read().unwrap().clone()
+}
+
+/// Sends requests over the network, used by default.
+pub struct HttpTransport {
+    client: HttpClient,
+}
+
+impl HttpTransport {
+    /// Creates a transport which follows redirects and identifies itself as airshipper.
+    pub fn new() -> Self {
+        let client = HttpClient::builder()
+            .redirect_policy(RedirectPolicy::Limit(MAX_REDIRECTS))
+            .default_header(
+                "User-Agent",
+                format!(
+                    "Airshipper/{} ({})",
+                    env!("CARGO_PKG_VERSION"),
+                    std::env::consts::OS
+                ),
+            )
+            .build()
+            .expect("failed to set up the http client!");
+        Self { client }
+    }
+}
+
+impl Default for HttpTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Transport for HttpTransport {
+    fn send(&self, request: Request<()>) -> BoxFuture<'_, Result<Response<Body>>> {
+        Box::pin(async move { Ok(self.client.send_async(request).await?) })
+    }
+}
+
+/// Answers requests with prepared responses without touching the network.
+/// Unknown urls result in `404 Not Found`.
+///
+/// ```
+/// use airshipper_core::network::{self, FakeTransport};
+///
+/// let fake = FakeTransport::new();
+/// fake.respond("https://download.veloren.net/version/linux/Nightly", 200, "v1");
+/// network::set_transport(fake.clone());
+/// ```
+#[derive(Clone, Default)]
+pub struct FakeTransport {
+    responses: Arc<Mutex<HashMap<String, (u16, Vec<u8>)>>>,
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl FakeTransport {
+    /// Creates a transport without any responses.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answers all requests of the url with the status and body.
+    pub fn respond<B: Into<Vec<u8>>>(&self, url: &str, status: u16, body: B) {
+        self.responses
+            .lock()
+            .unwrap()
+            .insert(url.to_owned(), (status, body.into()));
+    }
+
+    /// Returns the urls of all requests sent so far.
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+impl Transport for FakeTransport {
+    fn send(&self, request: Request<()>) -> BoxFuture<'_, Result<Response<Body>>> {
+        let url = request.uri().to_string();
+        self.requests.lock().unwrap().push(url.clone());
+        let (status, body) = self
+            .responses
+            .lock()
+            .unwrap()
+            .get(&url)
+            .cloned()
+            .unwrap_or_else(|| (StatusCode::NOT_FOUND.as_u16(), Vec::new()));
+
+        Box::pin(async move {
+            Ok(Response::builder()
+                .status(status)
+                .header("Content-Length", body.len())
+                .body(Body::from(body))?)
+        })
+    }
+}
@@ -0,0 +1,389 @@
+//! Profiles are independent installations of the game
+
+use crate::{error::ClientError, filesystem, lock::Lock, network, store, voxygen, Result};
+use derive_more::Display;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Port veloren servers listen on if none has been specified.
+pub const DEFAULT_SERVER_PORT: u16 = 14004;
+
+/// Represents a version with channel, name and path.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    /// Unique name chosen by the user
+    pub name: String,
+    /// Where the builds come from
+    pub channel: Channel,
+
+    /// Where the game is installed to
+    pub directory: PathBuf,
+    /// Installed version, empty if the game hasn't been installed yet
+    pub version: String,
+
+    /// Servers which can be joined directly on launch.
+    #[serde(default)]
+    pub favourite_servers: Vec<Server>,
+
+    /// When the game got started the last time (unix timestamp).
+    #[serde(default)]
+    pub last_launch: Option<i64>,
+}
+
+/// Disk space used by a profile in bytes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct DiskUsage {
+    /// Files of the installed build, see [`store`]
+    pub build: u64,
+    /// Part of `build` which other profiles use too, hence it is only stored once
+    pub shared: u64,
+    /// Everything else inside of the profile directory, e.g. settings, screenshots and logs
+    pub data: u64,
+    /// Downloaded build which waits to be installed
+    pub staged: u64,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Profile::new("default".to_owned(), Channel::Nightly)
+    }
+}
+
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+/// Kind of builds a profile receives.
+pub enum Channel {
+    /// Latest development build, updated daily
+    Nightly,
+    // TODO: Release,
+    // TODO: Source,
+}
+
+impl Channel {
+    /// All channels which can be chosen
+    pub const ALL: &'static [Channel] = &[Channel::Nightly];
+}
+
+impl std::str::FromStr for Channel {
+    type Err = ClientError;
+
+    fn from_str(channel: &str) -> Result<Self> {
+        Self::ALL
+            .iter()
+            .find(|x| x.to_string().eq_ignore_ascii_case(channel))
+            .copied()
+            .ok_or_else(|| format!("Unknown channel '{}'", channel).into())
+    }
+}
+
+impl Profile {
+    /// Creates a new profile and downloads the correct files into the target directory.
+    pub fn new(name: String, channel: Channel) -> Self {
+        Self {
+            directory: filesystem::get_profile_path(&name),
+            name,
+            channel,
+            version: "".to_owned(), // Will be set by download
+            favourite_servers: Vec::new(),
+            last_launch: None,
+        }
+    }
+
+    /// Starts downloading the latest build in the background, see [`network::start_download`].
+    pub async fn start_download(&self) -> Result<network::Progress> {
+        network::start_download(&self).await
+    }
+
+    /// Installs the downloaded build and returns the updated profile.
+    pub async fn install(self) -> Result<Profile> {
+        self.install_with_progress(Default::default()).await
+    }
+
+    /// Like [`Profile::install`] but reports which files got extracted to `progress`.
+    pub async fn install_with_progress(
+        mut self,
+        progress: network::InstallProgress,
+    ) -> Result<Profile> {
+        // TODO: maybe let install return the new profile or make it all &mut
+        self.version = network::install(&self, &progress).await?;
+        Ok(self)
+    }
+
+    // TODO: add possibility to start the server too
+    /// Starts voxygen and optionally selects `server` in its main menu.
+    pub fn start(&self, server: Option<&Server>) -> Result<()> {
+        // Keeps updates away even if the launcher gets closed while playing.
+        let lock = self.lock()?;
+        let mut envs = HashMap::new();
+        envs.insert("VOXYGEN_CONFIG", self.directory.clone().into_os_string());
+        if let Some(server) = server {
+            log::info!("Connecting to {}", server);
+            voxygen::set_default_server(&self.directory, server)?;
+        }
+
+        log::debug!("Launching {}", self.voxygen_path().display());
+        log::debug!("CWD: {:?}", self.directory);
+        log::debug!("ENV: {:?}", envs);
+
+        let mut child = lock.hand_over(
+            Command::new(self.voxygen_path())
+                .current_dir(&self.directory)
+                .envs(envs),
+        )?;
+        let cmd = child.wait()?;
+        log::debug!(
+            "Veloren exited with code: {}",
+            cmd.code()
+                .map(|x| x.to_string())
+                .unwrap_or("Exit code unavailable.".to_string())
+        );
+        Ok(())
+    }
+
+    /// Returns the latest version available for the channel.
+    pub async fn check_for_update(&self) -> Result<String> {
+        network::get_version(self.channel).await
+    }
+
+    /// Whether the game has been downloaded into the profile.
+    pub fn is_installed(&self) -> bool {
+        !self.version.is_empty() && self.voxygen_path().exists()
+    }
+
+    /// Returns how much space the profile takes up on disk.
+    pub fn disk_usage(&self) -> DiskUsage {
+        let (build, shared) = store::usage(&self.name);
+        DiskUsage {
+            build,
+            shared,
+            // The build files are links to the stored ones
+            data: filesystem::size(&self.directory).saturating_sub(build),
+            staged: filesystem::size(&filesystem::get_downloads_path().join(&self.name)),
+        }
+    }
+
+    /// Whether the name can be used for a profile (and its directory).
+    pub fn is_valid_name(name: &str) -> bool {
+        !name.is_empty()
+            && name != "."
+            && name != ".."
+            && name
+                .chars()
+                .all(|x| x.is_alphanumeric() || x == '-' || x == '_' || x == '.')
+    }
+
+    /// Locks the profile to prevent updating it while the game is running or
+    /// running the game while it gets updated.
+    pub fn lock(&self) -> Result<Lock> {
+        match Lock::acquire(self.directory.join(filesystem::PROFILE_LOCK_FILE))? {
+            Some(lock) => Ok(lock),
+            None => Err(format!(
+                "Profile '{}' is in use. Close Veloren or wait for the running update to finish.",
+                self.name
+            )
+            .into()),
+        }
+    }
+
+    /// Adds a server to the favourites.
+    /// Returns false if it's already one of them.
+    pub fn add_favourite(&mut self, server: Server) -> bool {
+        if self.favourite_servers.iter().any(|x| x.is(&server)) {
+            false
+        } else {
+            self.favourite_servers.push(server);
+            true
+        }
+    }
+
+    /// Removes a server from the favourites.
+    /// Returns false if it wasn't one of them.
+    pub fn remove_favourite(&mut self, server: &Server) -> bool {
+        let count = self.favourite_servers.len();
+        self.favourite_servers.retain(|x| !x.is(server));
+        count != self.favourite_servers.len()
+    }
+
+    /// Whether the game is running or being installed at the moment.
+    pub fn is_busy(&self) -> bool {
+        Lock::holder(self.directory.join(filesystem::PROFILE_LOCK_FILE)).is_some()
+    }
+
+    /// Returns the version of the completely downloaded build waiting to be installed.
+    pub fn staged_version(&self) -> Option<String> {
+        if !filesystem::get_download_path(&self.name).exists()
+            && !filesystem::get_extract_path(&self.name).exists()
+        {
+            return None;
+        }
+        std::fs::read_to_string(filesystem::get_staged_path(&self.name))
+            .ok()
+            .map(|x| x.trim().to_owned())
+    }
+
+    /// Remembers that the game is about to be started.
+    pub fn record_launch(&mut self) {
+        self.last_launch = Some(chrono::Utc::now().timestamp());
+    }
+
+    /// Returns the paths of all executables the game ships with.
+    pub fn executables(&self) -> Vec<PathBuf> {
+        let mut executables = vec![self.voxygen_path()];
+        #[cfg(unix)]
+        executables.push(self.directory.join(filesystem::SERVER_CLI_FILE));
+        executables
+    }
+
+    /// Returns path to voxygen binary.
+    /// e.g. <base>/profiles/latest/veloren-voxygen.exe
+    fn voxygen_path(&self) -> PathBuf {
+        self.directory.join(filesystem::VOXYGEN_FILE)
+    }
+}
+
+/// Multiplayer server address in the form of `host[:port]`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Server {
+    /// Domain or ip address
+    pub host: String,
+    /// Port, [`DEFAULT_SERVER_PORT`] unless specified otherwise
+    pub port: u16,
+}
+
+impl Server {
+    /// Whether both point to the same server.
+    pub fn is(&self, other: &Server) -> bool {
+        self.host.eq_ignore_ascii_case(&other.host) && self.port == other.port
+    }
+}
+
+impl std::fmt::Display for Server {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.host, self.port)
+    }
+}
+
+impl std::str::FromStr for Server {
+    type Err = ClientError;
+
+    fn from_str(address: &str) -> Result<Self> {
+        let address = address.trim();
+        let invalid = || ClientError::from(format!("Invalid server address '{}'", address));
+        let port = |port: &str| {
+            port.parse::<u16>()
+                .map_err(|_| format!("Invalid port in server address '{}'", address))
+        };
+
+        // IPv6 addresses contain colons themselves, the port follows the brackets
+        let (host, port) = if address.starts_with('[') {
+            let end = address.find(']').ok_or_else(invalid)?;
+            address[1..end]
+                .parse::<std::net::Ipv6Addr>()
+                .map_err(|_| invalid())?;
+            let port = match &address[end + 1..] {
+                "" => DEFAULT_SERVER_PORT,
+                x if x.starts_with(':') => port(&x[1..])?,
+                _ => return Err(invalid()),
+            };
+            (address[..=end].to_owned(), port)
+        } else {
+            match address.matches(':').count() {
+                0 => (address.to_owned(), DEFAULT_SERVER_PORT),
+                1 => {
+                    let idx = address.find(':').unwrap_or_default();
+                    (address[..idx].to_owned(), port(&address[idx + 1..])?)
+                }
+                // Bare IPv6 address, always without port
+                _ => {
+                    address
+                        .parse::<std::net::Ipv6Addr>()
+                        .map_err(|_| invalid())?;
+                    (format!("[{}]", address), DEFAULT_SERVER_PORT)
+                }
+            }
+        };
+
+        if host.is_empty() || host.contains(char::is_whitespace) {
+            return Err(invalid());
+        }
+
+        Ok(Self { host, port })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(address: &str) -> Result<(String, u16)> {
+        address.parse::<Server>().map(|x| (x.host, x.port))
+    }
+
+    #[test]
+    fn server_address() {
+        assert_eq!(
+            parse("example.com:14005").unwrap(),
+            ("example.com".into(), 14005)
+        );
+        assert_eq!(parse(" 127.0.0.1:1 ").unwrap(), ("127.0.0.1".into(), 1));
+        assert_eq!(parse("[::1]:14004").unwrap(), ("[::1]".into(), 14004));
+    }
+
+    #[test]
+    fn server_address_without_port() {
+        assert_eq!(
+            parse("example.com").unwrap(),
+            ("example.com".into(), DEFAULT_SERVER_PORT)
+        );
+        // A colon without port is a typo rather than the default port
+        assert!(parse("example.com:").is_err());
+    }
+
+    #[test]
+    fn ipv6_server_address() {
+        assert_eq!(
+            parse("[::1]").unwrap(),
+            ("[::1]".into(), DEFAULT_SERVER_PORT)
+        );
+        assert_eq!(parse("[::1]:14005").unwrap(), ("[::1]".into(), 14005));
+        // Without brackets the last group can't be told apart from a port
+        assert_eq!(parse("::1").unwrap(), ("[::1]".into(), DEFAULT_SERVER_PORT));
+        assert_eq!(
+            parse("2001:db8::1").unwrap(),
+            ("[2001:db8::1]".into(), DEFAULT_SERVER_PORT)
+        );
+        assert_eq!("::1".parse::<Server>().unwrap().to_string(), "[::1]:14004");
+        for address in &[
+            "[::1",
+            "[::1]14004",
+            "[::1]:",
+            "[example.com]:14004",
+            "1:2:x",
+        ] {
+            assert!(parse(address).is_err(), "{}", address);
+        }
+    }
+
+    #[test]
+    fn invalid_server_address() {
+        for address in &[
+            "",
+            ":14004",
+            "example.com:port",
+            "example.com:65536",
+            "example.com:-1",
+            "exam ple.com",
+        ] {
+            assert!(parse(address).is_err(), "{}", address);
+        }
+    }
+
+    #[test]
+    fn same_server() {
+        let server: Server = "Example.com".parse().unwrap();
+        assert!(server.is(&"example.COM:14004".parse().unwrap()));
+        assert!(!server.is(&"example.com:14005".parse().unwrap()));
+        assert_eq!(server.to_string().parse::<Server>().unwrap().port, 14004);
+    }
+}
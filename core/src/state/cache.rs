@@ -0,0 +1,62 @@
+//! Cached remote data which can be thrown away at any time
+
+use crate::{filesystem, network, Result};
+use serde::{Deserialize, Serialize};
+
+/// Remote data which is shown to the user
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct Cache {
+    /// Changes of the upcoming release
+    pub changelog: String,
+    /// Latest posts of the news feed
+    pub news: Vec<network::Post>,
+}
+
+impl Cache {
+    /// Loads the cache. A missing or corrupt cache results in an empty one.
+    pub async fn load() -> Self {
+        match super::read(filesystem::get_cache_file_path()).await {
+            Ok(Some(contents)) => ron::de::from_str(&contents).unwrap_or_else(|e| {
+                log::debug!("Dropping corrupt cache: {}", e);
+                Self::default()
+            }),
+            Ok(None) => Self::default(),
+            Err(e) => {
+                log::debug!("Failed to read cache: {}", e);
+                Self::default()
+            }
+        }
+    }
+
+    /// Fetches the changelog. Returns whether it changed.
+    pub async fn update_changelog(&mut self) -> Result<bool> {
+        let changelog = network::query_changelog().await?;
+        if changelog == self.changelog {
+            log::debug!("Changelog up-to-date.");
+            Ok(false)
+        } else {
+            self.changelog = changelog;
+            log::debug!("Changelog updated.");
+            Ok(true)
+        }
+    }
+
+    /// Fetches the news. Returns whether they changed.
+    pub async fn update_news(&mut self) -> Result<bool> {
+        let news = network::query_news().await?;
+        if news == self.news {
+            log::debug!("News up-to-date.");
+            Ok(false)
+        } else {
+            self.news = news;
+            log::debug!("News updated.");
+            Ok(true)
+        }
+    }
+
+    /// Writes the cache to disk.
+    pub async fn save(&self) -> Result<()> {
+        let ron = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        super::write(filesystem::get_cache_file_path(), &ron).await
+    }
+}
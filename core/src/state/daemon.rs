@@ -0,0 +1,95 @@
+//! Status the daemon reports to the other airshipper instances
+
+use crate::{filesystem, lock::Lock, Result};
+use serde::{Deserialize, Serialize};
+
+/// What the daemon is doing, written to disk for the other instances to read
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DaemonStatus {
+    /// Process id of the daemon
+    pub pid: u32,
+    /// What the daemon is doing at the moment
+    pub activity: Activity,
+    /// When the daemon checked for updates the last time (unix timestamp)
+    pub last_check: Option<i64>,
+    /// When the daemon is going to check for updates again (unix timestamp)
+    pub next_check: Option<i64>,
+    /// Builds which have been downloaded and wait to be installed
+    pub staged: Vec<StagedBuild>,
+    /// Why the last check failed, for each profile which failed
+    pub last_error: Option<String>,
+    /// Why the last pre-download got cancelled, e.g. as the game got started
+    #[serde(default)]
+    pub cancelled: Option<String>,
+}
+
+/// What the daemon is doing at the moment
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Activity {
+    /// Waiting for the next check
+    Idle,
+    /// Looking for updates
+    Checking,
+    /// Pre-downloading a new build
+    Downloading {
+        /// Name of the profile the build is for
+        profile: String,
+        /// Bytes downloaded so far
+        downloaded: u64,
+        /// Size of the build in bytes
+        total: u64,
+        /// Limit applied to the download in bytes per second
+        #[serde(default)]
+        rate_limit: Option<u64>,
+    },
+}
+
+/// Build which has been downloaded and waits to be installed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StagedBuild {
+    /// Name of the profile the build is for
+    pub profile: String,
+    /// Version of the build
+    pub version: String,
+    /// Bytes the build takes up until it gets installed
+    #[serde(default)]
+    pub size: u64,
+}
+
+impl Default for DaemonStatus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DaemonStatus {
+    /// Status of the current process which hasn't done anything yet.
+    pub fn new() -> Self {
+        Self {
+            pid: std::process::id(),
+            activity: Activity::Idle,
+            last_check: None,
+            next_check: None,
+            staged: Vec::new(),
+            last_error: None,
+            cancelled: None,
+        }
+    }
+
+    /// Returns the status of the running daemon, `None` if no daemon is running.
+    pub async fn load() -> Option<Self> {
+        Lock::holder(filesystem::get_daemon_lock_path())?;
+        let contents = super::read(filesystem::get_daemon_status_path())
+            .await
+            .ok()??;
+        ron::de::from_str(&contents)
+            .map_err(|e| log::debug!("Invalid daemon status: {}", e))
+            .ok()
+    }
+
+    /// Publishes the status to the other instances.
+    pub async fn save(&self) -> Result<()> {
+        let ron = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        super::write(filesystem::get_daemon_status_path(), &ron).await
+    }
+}
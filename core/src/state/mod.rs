@@ -0,0 +1,195 @@
+//! State which is used by the command line and GUI and also gets saved to disk
+//!
+//! It is split into the [`Settings`] which contain the user data and
+//! the [`Cache`] which can be thrown away at any time.
+
+mod cache;
+mod daemon;
+mod settings;
+
+pub use cache::Cache;
+pub use daemon::{Activity, DaemonStatus, StagedBuild};
+pub use settings::{Settings, UpdatePolicy};
+
+use crate::{filesystem, network, Result};
+use async_std::prelude::*;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// Amount of older versions kept of files which get backed up
+const BACKUPS: usize = 3;
+
+/// Everything airshipper keeps between runs
+#[derive(Default, Debug, Clone)]
+pub struct SavedState {
+    /// User data
+    pub settings: Settings,
+    /// Remote data
+    pub cache: Cache,
+    /// Message for the user which came up while loading (e.g. restored backup)
+    pub notice: Option<String>,
+}
+
+impl SavedState {
+    /// State to use until the real one has been loaded, must never be saved.
+    pub fn empty() -> Self {
+        Self {
+            ..Default::default()
+        }
+    }
+
+    /// Loads settings and cache.
+    /// State saved by older versions of airshipper will be migrated.
+    pub async fn load() -> Result<Self> {
+        if !filesystem::get_settings_path().exists() {
+            if let Some(contents) = read(filesystem::get_legacy_state_path()).await? {
+                return Self::migrate_legacy(&contents).await;
+            }
+        }
+
+        let (settings, backup) = Settings::load().await?;
+        let notice = backup.map(|path| {
+            let notice = format!(
+                "The settings were corrupt and have been restored from {}",
+                path.display()
+            );
+            log::warn!("{}", notice);
+            notice
+        });
+
+        Ok(Self {
+            settings,
+            cache: Cache::load().await,
+            notice,
+        })
+    }
+
+    /// Writes settings and cache to disk.
+    pub async fn save(self) -> Result<()> {
+        self.settings.save().await?;
+        self.cache.save().await?;
+        Ok(())
+    }
+
+    /// Splits the single state file used before settings
+    /// and cache got separated into both of them.
+    /// A corrupt state file gets backed up and replaced with the defaults.
+    async fn migrate_legacy(contents: &str) -> Result<Self> {
+        #[derive(Deserialize)]
+        struct LegacyState {
+            changelog: String,
+            news: Vec<network::Post>,
+            active_profile: crate::profiles::Profile,
+        }
+
+        log::info!("Migrating saved state to the new format...");
+        let path = filesystem::get_legacy_state_path();
+        let legacy: LegacyState = match ron::de::from_str(contents) {
+            Ok(legacy) => legacy,
+            Err(e) => {
+                log::error!("saved state invalid: {}", e);
+                backup(&path).await?;
+                async_std::fs::remove_file(&path).await?;
+                let notice = format!(
+                    "The saved state was corrupt and has been moved to {}",
+                    backup_path(&path, 1).display()
+                );
+                log::warn!("{}", notice);
+                let state = Self {
+                    notice: Some(notice),
+                    ..Default::default()
+                };
+                state.clone().save().await?;
+                return Ok(state);
+            }
+        };
+        let state = Self {
+            settings: Settings::with_profile(legacy.active_profile),
+            cache: Cache {
+                changelog: legacy.changelog,
+                news: legacy.news,
+            },
+            notice: None,
+        };
+
+        state.clone().save().await?;
+        async_std::fs::remove_file(path).await?;
+        Ok(state)
+    }
+}
+
+/// Reads the whole file, returns `None` if it does not exist.
+async fn read<P: AsRef<Path>>(path: P) -> Result<Option<String>> {
+    let mut contents = String::new();
+
+    match async_std::fs::File::open(path.as_ref()).await {
+        Ok(mut file) => {
+            file.read_to_string(&mut contents).await?;
+            Ok(Some(contents))
+        }
+        Err(e) => match e.kind() {
+            std::io::ErrorKind::NotFound => Ok(None),
+            _ => Err(e.into()),
+        },
+    }
+}
+
+/// Replaces the file with the given contents.
+///
+/// The contents get written to a temporary file first which replaces the original
+/// once it is safely on disk. A crash in between leaves the original untouched.
+async fn write<P: AsRef<Path>>(path: P, contents: &str) -> Result<()> {
+    let path = path.as_ref();
+    if let Some(dir) = path.parent() {
+        async_std::fs::create_dir_all(dir).await?;
+    }
+
+    let tmp = sibling(path, ".tmp");
+    let mut file = async_std::fs::File::create(&tmp).await?;
+    file.write_all(contents.as_bytes()).await?;
+    file.sync_all().await?;
+    drop(file);
+
+    async_std::fs::rename(&tmp, path).await?;
+
+    // Make sure the rename itself is persisted too
+    #[cfg(unix)]
+    {
+        if let Some(dir) = path.parent() {
+            std::fs::File::open(dir)?.sync_all()?;
+        }
+    }
+    Ok(())
+}
+
+/// Copies the file to the newest backup while rotating out the oldest one.
+async fn backup(path: &Path) -> Result<()> {
+    for i in (1..BACKUPS).rev() {
+        let older = backup_path(path, i);
+        if older.exists() {
+            async_std::fs::rename(&older, backup_path(path, i + 1)).await?;
+        }
+    }
+    async_std::fs::copy(path, backup_path(path, 1)).await?;
+    Ok(())
+}
+
+/// Returns all backups of the file which exist, starting with the newest.
+fn backups(path: &Path) -> Vec<PathBuf> {
+    (1..=BACKUPS)
+        .map(|i| backup_path(path, i))
+        .filter(|backup| backup.exists())
+        .collect()
+}
+
+/// e.g. airshipper_settings.ron.bak1
+fn backup_path(path: &Path, number: usize) -> PathBuf {
+    sibling(path, &format!(".bak{}", number))
+}
+
+/// Returns a path next to the file with the suffix appended to its name.
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
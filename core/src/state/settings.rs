@@ -0,0 +1,498 @@
+//! User data like the profiles which must never get lost
+
+use crate::{
+    error::ClientError,
+    filesystem,
+    network::{self, NetworkSettings},
+    profiles::{Channel, Profile},
+    Result,
+};
+use derive_more::Display;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Version of the settings format written by this airshipper.
+/// Bump it and add a migration to [`Settings::migrate`] when changing the format.
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// Profiles and preferences of the user
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Settings {
+    /// Schema version of the file these settings got loaded from
+    pub version: u32,
+    /// Name of the profile used unless another one has been requested
+    pub active_profile: String,
+    /// All profiles, always contains the active one
+    pub profiles: Vec<Profile>,
+    /// Whether the game gets updated before starting it
+    #[serde(default)]
+    pub game_updates: UpdatePolicy,
+    /// Whether airshipper updates itself (windows only)
+    #[serde(default)]
+    pub launcher_updates: UpdatePolicy,
+    /// Retries and download mirrors
+    #[serde(default)]
+    pub network: NetworkSettings,
+}
+
+/// What to do when an update is available.
+#[derive(Debug, Display, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum UpdatePolicy {
+    /// Update without asking
+    #[display(fmt = "always")]
+    Always,
+    /// Ask the user, unless running non-interactively
+    #[display(fmt = "ask")]
+    Ask,
+    /// Never update automatically
+    #[display(fmt = "never")]
+    Never,
+}
+
+impl UpdatePolicy {
+    /// All policies which can be chosen
+    pub const ALL: &'static [UpdatePolicy] =
+        &[UpdatePolicy::Always, UpdatePolicy::Ask, UpdatePolicy::Never];
+}
+
+impl Default for UpdatePolicy {
+    fn default() -> Self {
+        Self::Ask
+    }
+}
+
+impl std::str::FromStr for UpdatePolicy {
+    type Err = ClientError;
+
+    fn from_str(policy: &str) -> Result<Self> {
+        Self::ALL
+            .iter()
+            .find(|x| x.to_string().eq_ignore_ascii_case(policy))
+            .copied()
+            .ok_or_else(|| {
+                format!(
+                    "Unknown update policy '{}'. Possible values: always, ask, never",
+                    policy
+                )
+                .into()
+            })
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self::with_profile(Profile::default())
+    }
+}
+
+impl Settings {
+    /// Creates settings with the profile as the only and active one.
+    pub fn with_profile(profile: Profile) -> Self {
+        Self {
+            version: SCHEMA_VERSION,
+            active_profile: profile.name.clone(),
+            profiles: vec![profile],
+            game_updates: UpdatePolicy::default(),
+            launcher_updates: UpdatePolicy::default(),
+            network: NetworkSettings::default(),
+        }
+    }
+
+    /// Returns the active profile.
+    pub fn active_profile(&self) -> &Profile {
+        self.profile(&self.active_profile)
+            .expect("active profile is missing!")
+    }
+
+    /// Returns the active profile.
+    pub fn active_profile_mut(&mut self) -> &mut Profile {
+        let name = self.active_profile.clone();
+        self.profile_mut(&name).expect("active profile is missing!")
+    }
+
+    /// Returns the profile with the given name.
+    pub fn profile(&self, name: &str) -> Option<&Profile> {
+        self.profiles.iter().find(|x| x.name == name)
+    }
+
+    /// Returns the profile with the given name.
+    pub fn profile_mut(&mut self, name: &str) -> Option<&mut Profile> {
+        self.profiles.iter_mut().find(|x| x.name == name)
+    }
+
+    /// Returns the requested profile or the active one if none has been requested.
+    pub fn select_profile_mut(&mut self, name: Option<&str>) -> Result<&mut Profile> {
+        match name {
+            Some(name) => self
+                .profile_mut(name)
+                .ok_or_else(|| format!("Profile '{}' does not exist!", name).into()),
+            None => Ok(self.active_profile_mut()),
+        }
+    }
+
+    /// Adds the profile, its name must not be taken already.
+    pub fn add_profile(&mut self, profile: Profile) -> Result<()> {
+        if self.profile(&profile.name).is_some() {
+            return Err(format!("Profile '{}' already exists!", profile.name).into());
+        }
+        self.profiles.push(profile);
+        Ok(())
+    }
+
+    /// Removes the profile from the settings, its files are left untouched.
+    pub fn remove_profile(&mut self, name: &str) -> Result<Profile> {
+        if name == self.active_profile {
+            return Err(format!(
+                "Profile '{}' is the active one. Switch to another profile first.",
+                name
+            )
+            .into());
+        }
+        match self.profiles.iter().position(|x| x.name == name) {
+            Some(idx) => Ok(self.profiles.remove(idx)),
+            None => Err(format!("Profile '{}' does not exist!", name).into()),
+        }
+    }
+
+    /// Makes the profile with the given name the active one.
+    pub fn set_active_profile(&mut self, name: &str) -> Result<()> {
+        if self.profile(name).is_none() {
+            return Err(format!("Profile '{}' does not exist!", name).into());
+        }
+        self.active_profile = name.to_owned();
+        Ok(())
+    }
+
+    /// Returns all settings which can be changed via `get`/`set` with their current value.
+    pub fn values(&self) -> Vec<(String, String)> {
+        let mut values = vec![
+            ("active_profile".into(), self.active_profile.clone()),
+            ("game_updates".into(), self.game_updates.to_string()),
+            ("launcher_updates".into(), self.launcher_updates.to_string()),
+            ("retries".into(), self.network.retries.to_string()),
+            ("segments".into(), self.network.segments.to_string()),
+            (
+                "proxy".into(),
+                self.network.proxy.clone().unwrap_or_default(),
+            ),
+            (
+                "max_rate".into(),
+                self.network
+                    .max_rate
+                    .map(|x| (x / 1024).to_string())
+                    .unwrap_or_default(),
+            ),
+            (
+                "download_window".into(),
+                self.network
+                    .download_window
+                    .map(|x| x.to_string())
+                    .unwrap_or_default(),
+            ),
+            (
+                "ca_certificates".into(),
+                std::env::join_paths(&self.network.ca_certificates)
+                    .map(|x| x.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+            ),
+        ];
+        for channel in Channel::ALL {
+            let mirrors = self.network.mirrors.get(channel).cloned();
+            values.push((mirrors_key(*channel), mirrors.unwrap_or_default().join(",")));
+        }
+        values
+    }
+
+    /// Returns the value of a setting listed by [`Settings::values`].
+    pub fn get(&self, key: &str) -> Result<String> {
+        self.values()
+            .into_iter()
+            .find(|(x, _)| *x == key)
+            .map(|(_, value)| value)
+            .ok_or_else(|| format!("Unknown setting '{}'", key).into())
+    }
+
+    /// Changes a setting listed by [`Settings::values`].
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        match key {
+            "active_profile" => self.set_active_profile(value),
+            "game_updates" => {
+                self.game_updates = value.parse()?;
+                Ok(())
+            }
+            "launcher_updates" => {
+                self.launcher_updates = value.parse()?;
+                Ok(())
+            }
+            "retries" => {
+                self.network.retries = value
+                    .parse()
+                    .map_err(|_| format!("Invalid amount of retries '{}'", value))?;
+                network::configure(&self.network);
+                Ok(())
+            }
+            "segments" => {
+                self.network.segments = value
+                    .parse()
+                    .ok()
+                    .filter(|x| *x > 0)
+                    .ok_or_else(|| format!("Invalid amount of segments '{}'", value))?;
+                network::configure(&self.network);
+                Ok(())
+            }
+            "proxy" => {
+                let proxy = value.trim();
+                if !proxy.is_empty() {
+                    network::validate_proxy(proxy)?;
+                }
+                self.network.proxy = Some(proxy.to_owned()).filter(|x| !x.is_empty());
+                network::configure(&self.network);
+                Ok(())
+            }
+            "max_rate" => {
+                // KiB/s like the command line, zero means unlimited
+                let max_rate: u64 = match value.trim() {
+                    "" => 0,
+                    x => x
+                        .parse()
+                        .map_err(|_| format!("Invalid download limit '{}' (KiB/s)", value))?,
+                };
+                self.network.max_rate = Some(max_rate * 1024).filter(|x| *x > 0);
+                network::configure(&self.network);
+                Ok(())
+            }
+            "download_window" => {
+                self.network.download_window = match value.trim() {
+                    "" => None,
+                    x => Some(x.parse()?),
+                };
+                Ok(())
+            }
+            "ca_certificates" => {
+                let certificates = std::env::split_paths(value)
+                    .filter(|x| !x.as_os_str().is_empty())
+                    .collect::<Vec<_>>();
+                if let Some(missing) = certificates.iter().find(|x| !x.is_file()) {
+                    return Err(
+                        format!("Certificate '{}' does not exist!", missing.display()).into(),
+                    );
+                }
+                self.network.ca_certificates = certificates;
+                network::configure(&self.network);
+                Ok(())
+            }
+            _ => match Channel::ALL.iter().find(|x| mirrors_key(**x) == key) {
+                Some(channel) => {
+                    let mirrors = value
+                        .split(',')
+                        .map(|x| x.trim().trim_end_matches('/').to_owned())
+                        .filter(|x| !x.is_empty())
+                        .collect::<Vec<_>>();
+                    for mirror in &mirrors {
+                        url::Url::parse(mirror)
+                            .map_err(|e| format!("Invalid mirror '{}': {}", mirror, e))?;
+                    }
+                    self.network.mirrors.insert(*channel, mirrors);
+                    network::configure(&self.network);
+                    Ok(())
+                }
+                None => Err(format!("Unknown setting '{}'", key).into()),
+            },
+        }
+    }
+
+    /// Loads the settings and falls back to the newest valid backup if they are
+    /// missing or corrupt. Returns the path of the backup in case one got used.
+    ///
+    /// The network settings get applied to all following requests.
+    pub async fn load() -> Result<(Self, Option<PathBuf>)> {
+        let (settings, backup) = Self::load_or_restore().await?;
+        network::configure(&settings.network);
+        Ok((settings, backup))
+    }
+
+    /// Reads the settings like [`Settings::load`] without applying the network settings.
+    pub async fn read() -> Result<Self> {
+        Ok(Self::load_or_restore().await?.0)
+    }
+
+    async fn load_or_restore() -> Result<(Self, Option<PathBuf>)> {
+        let path = filesystem::get_settings_path();
+        let error = match super::read(&path).await? {
+            Some(contents) => match Self::parse(&contents) {
+                Ok(settings) => return Ok((settings, None)),
+                Err(e) => {
+                    log::error!("settings invalid: {}", e);
+                    Some(e)
+                }
+            },
+            None => None,
+        };
+
+        for backup in super::backups(&path) {
+            match super::read(&backup).await?.map(|x| Self::parse(&x)) {
+                Some(Ok(settings)) => return Ok((settings, Some(backup))),
+                Some(Err(e)) => log::debug!("Skipping invalid backup {}: {}", backup.display(), e),
+                None => {}
+            }
+        }
+
+        match error {
+            Some(e) => Err(e),
+            None => {
+                log::debug!("settings not found. Fallback to default!");
+                Ok((Self::default(), None))
+            }
+        }
+    }
+
+    /// Writes the settings to disk while keeping a backup of the previous ones.
+    pub async fn save(&self) -> Result<()> {
+        let ron = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        let path = filesystem::get_settings_path();
+
+        match super::read(&path).await? {
+            // Avoids rotating out older backups for nothing
+            Some(current) if current == ron => return Ok(()),
+            // Only working settings are worth a backup
+            Some(current) if Self::parse(&current).is_ok() => super::backup(&path).await?,
+            _ => {}
+        }
+        super::write(&path, &ron).await
+    }
+
+    /// Parses settings of any known schema version.
+    fn parse(contents: &str) -> Result<Self> {
+        #[derive(Deserialize)]
+        struct Schema {
+            version: u32,
+        }
+
+        let Schema { version } = ron::de::from_str(contents)?;
+        if version > SCHEMA_VERSION {
+            return Err(format!(
+                "The settings have been saved by a newer version of Airshipper (schema v{}). Please update Airshipper.",
+                version
+            )
+            .into());
+        }
+
+        let mut settings = Self::migrate(version, contents)?;
+        if settings.profiles.is_empty() {
+            settings.profiles.push(Profile::default());
+        }
+        if settings.profile(&settings.active_profile).is_none() {
+            settings.active_profile = settings.profiles[0].name.clone();
+        }
+        for profile in &mut settings.profiles {
+            // The base path might have moved (e.g. portable install on another drive)
+            profile.directory = filesystem::get_profile_path(&profile.name);
+        }
+        Ok(settings)
+    }
+
+    /// Migrates settings from older schema versions step by step.
+    fn migrate(version: u32, contents: &str) -> Result<Self> {
+        match version {
+            // Only a single profile existed
+            1 => {
+                #[derive(Deserialize)]
+                struct V1 {
+                    active_profile: Profile,
+                }
+
+                let V1 { active_profile } = ron::de::from_str(contents)?;
+                Ok(Self::with_profile(active_profile))
+            }
+            SCHEMA_VERSION => Ok(ron::de::from_str(contents)?),
+            _ => Err(format!("Unknown settings schema v{}!", version).into()),
+        }
+    }
+}
+
+/// e.g. `nightly_mirrors`
+fn mirrors_key(channel: Channel) -> String {
+    format!("{}_mirrors", channel.to_string().to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// First version of the settings format which only supported a single profile
+    const V1: &str = r#"(
+    version: 1,
+    active_profile: (
+        name: "default",
+        channel: Nightly,
+        directory: "/home/player/.local/share/airshipper/profiles/default",
+        version: "1a2b3c4d",
+        favourite_servers: [
+            (
+                host: "server.veloren.net",
+                port: 14004,
+            ),
+        ],
+    ),
+)"#;
+
+    #[test]
+    fn migrate_v1() {
+        let settings = Settings::migrate(1, V1).unwrap();
+        assert_eq!(settings.version, SCHEMA_VERSION);
+        assert_eq!(settings.active_profile, "default");
+        assert_eq!(settings.profiles.len(), 1);
+
+        let profile = settings.active_profile();
+        assert_eq!(profile.version, "1a2b3c4d");
+        assert_eq!(profile.channel, Channel::Nightly);
+        assert_eq!(profile.favourite_servers.len(), 1);
+        assert_eq!(profile.favourite_servers[0].host, "server.veloren.net");
+        assert_eq!(profile.last_launch, None);
+        assert_eq!(settings.game_updates, UpdatePolicy::Ask);
+        assert_eq!(settings.network, NetworkSettings::default());
+    }
+
+    #[test]
+    fn migrate_v1_without_optional_fields() {
+        let contents = r#"(version: 1, active_profile: (name: "old", channel: Nightly, directory: "profiles/old", version: ""))"#;
+        let settings = Settings::migrate(1, contents).unwrap();
+        assert_eq!(settings.active_profile, "old");
+        assert!(settings.active_profile().favourite_servers.is_empty());
+    }
+
+    #[test]
+    fn parse_creates_no_directories() {
+        let contents = V1.replace("\"default\"", "\"never-installed\"");
+        let settings = Settings::parse(&contents).unwrap();
+        let profile = settings.active_profile();
+        assert_eq!(
+            profile.directory,
+            filesystem::get_profile_path("never-installed")
+        );
+        assert!(!profile.directory.exists());
+    }
+
+    #[test]
+    fn current_schema() {
+        let v1 = Settings::migrate(1, V1).unwrap();
+        let ron = ron::ser::to_string_pretty(&v1, ron::ser::PrettyConfig::default()).unwrap();
+        let settings = Settings::migrate(SCHEMA_VERSION, &ron).unwrap();
+        assert_eq!(settings.active_profile, v1.active_profile);
+        assert_eq!(settings.profiles.len(), 1);
+        assert_eq!(settings.network, v1.network);
+        assert_eq!(
+            ron::ser::to_string_pretty(&settings, ron::ser::PrettyConfig::default()).unwrap(),
+            ron
+        );
+    }
+
+    #[test]
+    fn unknown_schema() {
+        assert!(Settings::migrate(0, V1).is_err());
+        let newer = V1.replace("version: 1,", &format!("version: {},", SCHEMA_VERSION + 1));
+        let error = Settings::parse(&newer).unwrap_err().to_string();
+        assert!(error.contains("newer version of Airshipper"), "{}", error);
+        assert!(Settings::parse("(active_profile: ())").is_err());
+    }
+}
@@ -0,0 +1,289 @@
+//! Content-addressed store keeping a single copy of every file of the installed builds.
+//!
+//! Files are stored under their SHA-256 hash and hard-linked into the profiles, hence
+//! profiles sharing most of their assets take up the space of a single one. Every profile
+//! has a manifest listing the stored files it uses, files no manifest refers to anymore
+//! get deleted by [`collect_garbage`].
+//!
+//! Stored files must never be modified in place as all profiles share them.
+//! Copies are made instead of links if the filesystem doesn't support hard links.
+
+use crate::{filesystem, lock::Lock, network::InstallProgress, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    io::Read,
+    path::{Component, Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+/// How long to wait for another process working on the store
+const LOCK_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Files of a profile which are linked from the store
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Manifest {
+    /// Where the profile is installed, its files are no longer used once it's gone
+    directory: PathBuf,
+    /// Path relative to the profile (separated by `/`) and hash of the file
+    files: BTreeMap<String, String>,
+}
+
+/// Moves all files of the extracted build into the store and links them into `target`,
+/// replacing the previous build of the profile. Leaves the other files of `target` alone.
+/// `hashes` contains the hashes known already (see [`name`]), the other files get hashed.
+pub(crate) fn import(
+    build: &Path,
+    target: &Path,
+    profile: &str,
+    hashes: &HashMap<String, String>,
+    progress: &InstallProgress,
+) -> Result<()> {
+    let _lock = lock()?;
+    let previous = load(profile);
+    // Builds installed before the store don't tell which assets are obsolete
+    if previous.is_none() {
+        filesystem::remove(&target.join("assets"))?;
+    }
+    let files = walk(build)?;
+    progress.start(
+        files.len() as u64,
+        files.iter().map(|x| filesystem::size(&build.join(x))).sum(),
+    );
+
+    let mut manifest = Manifest {
+        directory: target.to_owned(),
+        files: BTreeMap::new(),
+    };
+    let mut linked = 0;
+    for relative in files {
+        let source = build.join(&relative);
+        let size = filesystem::size(&source);
+        let name = name(&relative);
+        let hash = match hashes.get(&name) {
+            Some(hash) => hash.clone(),
+            None => hash(&source)?,
+        };
+        let object = object_path(&hash);
+        if object.exists() {
+            std::fs::remove_file(&source)?;
+        } else {
+            std::fs::create_dir_all(object.parent().unwrap())?;
+            filesystem::move_file(&source, &object)?;
+        }
+
+        let destination = target.join(&relative);
+        if let Some(parent) = destination.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        filesystem::remove(&destination)?;
+        if std::fs::hard_link(&object, &destination).is_ok() {
+            linked += 1;
+        } else {
+            std::fs::copy(&object, &destination)?;
+        }
+
+        progress.add(name.clone(), size);
+        manifest.files.insert(name, hash);
+    }
+    if linked < manifest.files.len() {
+        log::warn!(
+            "Only {} of {} files could be linked, the rest has been copied.",
+            linked,
+            manifest.files.len()
+        );
+    }
+
+    // Files which were part of the previous build only
+    if let Some(previous) = previous {
+        for path in previous.files.keys() {
+            if !manifest.files.contains_key(path) {
+                filesystem::remove(&target.join(path))?;
+            }
+        }
+    }
+    save(profile, &manifest)
+}
+
+/// Stops keeping the files of the profile, e.g. after it has been deleted.
+/// They get removed from the store by the next [`collect_garbage`].
+pub fn forget(profile: &str) -> Result<()> {
+    let _lock = lock()?;
+    filesystem::remove(&manifest_path(profile))?;
+    Ok(())
+}
+
+/// Deletes all stored files which no profile uses anymore and returns the freed bytes.
+pub fn collect_garbage() -> Result<u64> {
+    let _lock = lock()?;
+    let mut references = HashMap::<String, usize>::new();
+    for path in entries(&filesystem::get_store_path().join("manifests")) {
+        let manifest = match read(&path) {
+            Some(manifest) => manifest,
+            None => continue,
+        };
+        if !manifest.directory.exists() {
+            log::debug!(
+                "Dropping manifest of removed {}",
+                manifest.directory.display()
+            );
+            filesystem::remove(&path)?;
+            continue;
+        }
+        for hash in manifest.files.values() {
+            *references.entry(hash.clone()).or_default() += 1;
+        }
+    }
+
+    let mut freed = 0;
+    for directory in entries(&filesystem::get_store_path().join("objects")) {
+        for object in entries(&directory) {
+            let hash = object.file_name().map(|x| x.to_string_lossy().into_owned());
+            if hash.map_or(false, |x| !references.contains_key(&x)) {
+                freed += filesystem::size(&object);
+                filesystem::remove(&object)?;
+            }
+        }
+    }
+    if freed > 0 {
+        log::info!("Removed {} bytes of unused files from the store.", freed);
+    }
+    Ok(freed)
+}
+
+/// Returns the bytes of the stored files the profile uses
+/// and how many of them other profiles use too.
+pub fn usage(profile: &str) -> (u64, u64) {
+    let manifest = match load(profile) {
+        Some(manifest) => manifest,
+        None => return (0, 0),
+    };
+    let own = manifest_path(profile);
+    let others = entries(&filesystem::get_store_path().join("manifests"))
+        .into_iter()
+        .filter(|x| *x != own)
+        .filter_map(|x| read(&x))
+        .flat_map(|x| x.files.into_iter().map(|(_, hash)| hash))
+        .collect::<HashSet<_>>();
+
+    let (mut used, mut shared) = (0, 0);
+    for hash in manifest.files.values() {
+        let size = filesystem::size(&object_path(hash));
+        used += size;
+        if others.contains(hash) {
+            shared += size;
+        }
+    }
+    (used, shared)
+}
+
+/// Returns the size of all stored files.
+pub fn size() -> u64 {
+    filesystem::size(&filesystem::get_store_path().join("objects"))
+}
+
+/// Waits until no other process works on the store.
+fn lock() -> Result<Lock> {
+    let path = filesystem::get_store_path();
+    std::fs::create_dir_all(&path)?;
+    let started = Instant::now();
+    loop {
+        if let Some(lock) = Lock::acquire(path.join("store.lock"))? {
+            return Ok(lock);
+        }
+        if started.elapsed() > LOCK_TIMEOUT {
+            return Err("The store is in use by another process.".into());
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+}
+
+/// Returns the hex encoded SHA-256 hash of the file.
+fn hash(path: &Path) -> Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        match file.read(&mut buffer)? {
+            0 => break,
+            x => hasher.input(&buffer[..x]),
+        }
+    }
+    Ok(hex(&hasher.result()))
+}
+
+/// Hex encodes a hash the way the store names its files.
+pub(crate) fn hex(hash: &[u8]) -> String {
+    hash.iter().map(|x| format!("{:02x}", x)).collect()
+}
+
+/// Name of a file in the manifest: its path relative to the profile, separated by `/`.
+pub(crate) fn name(relative: &Path) -> String {
+    relative
+        .components()
+        .filter_map(|x| match x {
+            Component::Normal(x) => Some(x.to_string_lossy()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// e.g. <store>/objects/3f/3f2a...
+fn object_path(hash: &str) -> PathBuf {
+    filesystem::get_store_path()
+        .join("objects")
+        .join(&hash[..2])
+        .join(hash)
+}
+
+fn manifest_path(profile: &str) -> PathBuf {
+    filesystem::get_store_path()
+        .join("manifests")
+        .join(format!("{}.ron", profile))
+}
+
+fn load(profile: &str) -> Option<Manifest> {
+    read(&manifest_path(profile))
+}
+
+fn read(path: &Path) -> Option<Manifest> {
+    let contents = std::fs::read_to_string(path).ok()?;
+    ron::de::from_str(&contents)
+        .map_err(|e| log::warn!("Ignoring corrupt manifest {}: {}", path.display(), e))
+        .ok()
+}
+
+fn save(profile: &str, manifest: &Manifest) -> Result<()> {
+    let path = manifest_path(profile);
+    std::fs::create_dir_all(path.parent().unwrap())?;
+    std::fs::write(path, ron::ser::to_string(manifest)?)?;
+    Ok(())
+}
+
+/// Returns the entries of the directory, none if it doesn't exist.
+fn entries(directory: &Path) -> Vec<PathBuf> {
+    std::fs::read_dir(directory)
+        .map(|x| x.filter_map(|x| x.ok()).map(|x| x.path()).collect())
+        .unwrap_or_default()
+}
+
+/// Returns all files inside of the directory relative to it.
+fn walk(directory: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut pending = vec![PathBuf::new()];
+    while let Some(relative) = pending.pop() {
+        for entry in std::fs::read_dir(directory.join(&relative))? {
+            let entry = entry?;
+            let path = relative.join(entry.file_name());
+            if entry.file_type()?.is_dir() {
+                pending.push(path);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
@@ -0,0 +1,387 @@
+//! Edits the settings of Voxygen before it gets started.
+//!
+//! Voxygen reads `settings.ron` from the directory in `VOXYGEN_CONFIG` and uses defaults for
+//! missing fields. Only the fields which have to change get replaced, the rest of the file
+//! (including comments) stays as the player left it.
+
+use crate::{profiles::Server, Result};
+use std::{ops::Range, path::Path};
+
+/// Name of the settings file inside of `VOXYGEN_CONFIG`
+const SETTINGS_FILE: &str = "settings.ron";
+
+/// Makes `server` the one the main menu connects to.
+pub(crate) fn set_default_server(directory: &Path, server: &Server) -> Result<()> {
+    let path = directory.join(SETTINGS_FILE);
+    let settings = match std::fs::read_to_string(&path) {
+        Ok(settings) => settings,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e.into()),
+    };
+    let settings = with_default_server(&settings, server).ok_or_else(|| {
+        format!(
+            "Couldn't read the Voxygen settings at {}. Fix or remove them to connect to {}.",
+            path.display(),
+            server
+        )
+    })?;
+    std::fs::write(path, settings)?;
+    Ok(())
+}
+
+/// Adds the server to `networking.servers` unless it's in there already and points
+/// `networking.default_server` to it. Returns `None` if the settings can't be parsed.
+fn with_default_server(settings: &str, server: &Server) -> Option<String> {
+    if settings.trim().is_empty() {
+        return Some(format!(
+            "(\n    networking: (\n        servers: [{}],\n        default_server: 0,\n    ),\n)\n",
+            quote(&server.to_string())
+        ));
+    }
+
+    let root = struct_body(settings, 0..settings.len())?;
+    let networking = match field(&fields(settings, root.clone())?, "networking") {
+        Some(value) => struct_body(settings, value)?,
+        None => {
+            let field = format!(
+                "\n    networking: (servers: [{}], default_server: 0),",
+                quote(&server.to_string())
+            );
+            return Some(insert(settings, root.start, &field));
+        }
+    };
+
+    let networking_fields = fields(settings, networking.clone())?;
+    let mut servers = match field(&networking_fields, "servers") {
+        Some(value) => Some((value.clone(), strings(settings, value)?)),
+        None => None,
+    };
+    let index = match &mut servers {
+        Some((_, list)) => match list
+            .iter()
+            .position(|x| x.parse::<Server>().map_or(false, |x| x.is(server)))
+        {
+            Some(index) => index,
+            None => {
+                list.push(server.to_string());
+                list.len() - 1
+            }
+        },
+        None => 0,
+    };
+
+    // Edits from the end of the file first as they move everything behind them
+    let mut edits = Vec::new();
+    match field(&networking_fields, "default_server") {
+        Some(value) => edits.push((value, index.to_string())),
+        None => edits.push((
+            networking.start..networking.start,
+            format!(" default_server: {},", index),
+        )),
+    }
+    let list = |list: &[String]| {
+        let list = list.iter().map(|x| quote(x)).collect::<Vec<_>>();
+        format!("[{}]", list.join(", "))
+    };
+    match servers {
+        Some((value, servers)) => edits.push((value, list(&servers))),
+        None => edits.push((
+            networking.start..networking.start,
+            format!(" servers: {},", list(&[server.to_string()])),
+        )),
+    }
+    edits.sort_by_key(|(range, _)| std::cmp::Reverse(range.start));
+
+    let mut settings = settings.to_owned();
+    for (range, text) in edits {
+        settings.replace_range(range, &text);
+    }
+    Some(settings)
+}
+
+fn insert(text: &str, at: usize, insertion: &str) -> String {
+    let mut text = text.to_owned();
+    text.insert_str(at, insertion);
+    text
+}
+
+/// Returns the range between the parentheses of a struct like `Name(...)` or `(...)`.
+fn struct_body(text: &str, value: Range<usize>) -> Option<Range<usize>> {
+    let mut start = skip_ignored(text, value.start);
+    while text.as_bytes().get(start).map_or(false, |&x| is_ident(x)) {
+        start += 1;
+    }
+    start = skip_ignored(text, start);
+    if text.as_bytes().get(start) != Some(&b'(') {
+        return None;
+    }
+    let end = value_end(text, start)?;
+    if skip_ignored(text, end) < value.end || text.as_bytes()[end - 1] != b')' {
+        return None;
+    }
+    Some(start + 1..end - 1)
+}
+
+/// Returns the names of the fields inside of a struct body and the ranges of their values.
+fn fields(text: &str, body: Range<usize>) -> Option<Vec<(&str, Range<usize>)>> {
+    let mut fields = Vec::new();
+    let mut position = body.start;
+    loop {
+        position = skip_ignored(text, position);
+        if position >= body.end {
+            return Some(fields);
+        }
+        let start = position;
+        while position < body.end && is_ident(text.as_bytes()[position]) {
+            position += 1;
+        }
+        let ident = &text[start..position];
+        position = skip_ignored(text, position);
+        if ident.is_empty() || text.as_bytes().get(position) != Some(&b':') {
+            return None;
+        }
+        let value_start = skip_ignored(text, position + 1);
+        position = value_end(text, value_start)?;
+        fields.push((ident, value_start..position));
+        position = next_element(text, position, body.end)?;
+    }
+}
+
+fn field(fields: &[(&str, Range<usize>)], name: &str) -> Option<Range<usize>> {
+    fields
+        .iter()
+        .find(|(ident, _)| *ident == name)
+        .map(|(_, value)| value.clone())
+}
+
+/// Returns the strings of a list like `["a", "b"]`.
+fn strings(text: &str, value: Range<usize>) -> Option<Vec<String>> {
+    let bytes = text.as_bytes();
+    if value.end - value.start < 2 || bytes[value.start] != b'[' || bytes[value.end - 1] != b']' {
+        return None;
+    }
+    let end = value.end - 1;
+    let mut position = value.start + 1;
+    let mut strings = Vec::new();
+    loop {
+        position = skip_ignored(text, position);
+        if position >= end {
+            return Some(strings);
+        }
+        let start = position;
+        position = value_end(text, start)?;
+        strings.push(unquote(&text[start..position])?);
+        position = next_element(text, position, end)?;
+    }
+}
+
+/// Skips the comma after an element, returns `None` if something else follows.
+fn next_element(text: &str, position: usize, end: usize) -> Option<usize> {
+    let position = skip_ignored(text, position);
+    match text.as_bytes().get(position) {
+        Some(b',') => Some(position + 1),
+        _ if position >= end => Some(position),
+        _ => None,
+    }
+}
+
+/// Returns where the value starting at `start` ends.
+fn value_end(text: &str, start: usize) -> Option<usize> {
+    let bytes = text.as_bytes();
+    let mut depth = 0;
+    let mut position = start;
+    while position < bytes.len() {
+        match bytes[position] {
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' if depth == 0 => break,
+            b')' | b']' | b'}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(position + 1);
+                }
+            }
+            b',' if depth == 0 => break,
+            b'"' => {
+                position = string_end(bytes, position)?;
+                continue;
+            }
+            b'/' if matches!(bytes.get(position + 1), Some(b'/') | Some(b'*')) => {
+                if depth == 0 {
+                    break;
+                }
+                position = skip_ignored(text, position);
+                continue;
+            }
+            _ => {}
+        }
+        position += 1;
+    }
+    if depth != 0 {
+        return None;
+    }
+    // Whitespace in front of a comma or comment isn't part of the value
+    Some(start + text[start..position].trim_end().len())
+}
+
+/// Returns the position after the closing quote of the string starting at `start`.
+fn string_end(bytes: &[u8], start: usize) -> Option<usize> {
+    let mut position = start + 1;
+    while position < bytes.len() {
+        match bytes[position] {
+            b'\\' => position += 2,
+            b'"' => return Some(position + 1),
+            _ => position += 1,
+        }
+    }
+    None
+}
+
+/// Skips whitespace and comments.
+fn skip_ignored(text: &str, mut position: usize) -> usize {
+    let bytes = text.as_bytes();
+    loop {
+        match (bytes.get(position), bytes.get(position + 1)) {
+            (Some(x), _) if x.is_ascii_whitespace() => position += 1,
+            (Some(b'/'), Some(b'/')) => {
+                position = text[position..]
+                    .find('\n')
+                    .map_or(bytes.len(), |x| position + x + 1)
+            }
+            (Some(b'/'), Some(b'*')) => {
+                position = text[position + 2..]
+                    .find("*/")
+                    .map_or(bytes.len(), |x| position + x + 4)
+            }
+            _ => return position,
+        }
+    }
+}
+
+fn is_ident(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_'
+}
+
+fn quote(string: &str) -> String {
+    format!("\"{}\"", string.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn unquote(literal: &str) -> Option<String> {
+    if literal.len() < 2 || !literal.starts_with('"') || !literal.ends_with('"') {
+        return None;
+    }
+    let mut string = String::new();
+    let mut chars = literal[1..literal.len() - 1].chars();
+    while let Some(x) = chars.next() {
+        match x {
+            '\\' => string.push(chars.next()?),
+            x => string.push(x),
+        }
+    }
+    Some(string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    /// The part of Voxygen's settings which gets edited
+    #[derive(Debug, Deserialize)]
+    struct Settings {
+        networking: Networking,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Networking {
+        #[serde(default)]
+        username: String,
+        servers: Vec<String>,
+        default_server: usize,
+    }
+
+    fn server(address: &str) -> Server {
+        address.parse().unwrap()
+    }
+
+    fn networking(settings: &str) -> Networking {
+        ron::de::from_str::<Settings>(settings).unwrap().networking
+    }
+
+    const SETTINGS: &str = r#"(
+    gameplay: (
+        pan_sensitivity: 100,
+        // Voxygen keeps unknown fields and enums as they are
+        crosshair_type: Round,
+    ),
+    networking: (
+        username: "player",
+        servers: [
+            "server.veloren.net",
+            "example.com:14005",
+        ],
+        default_server: 0,
+        trusted_auth_servers: ["https://auth.veloren.net"],
+    ),
+    audio: (
+        output: Automatic,
+    ),
+)
+"#;
+
+    #[test]
+    fn creates_settings() {
+        let settings = with_default_server("", &server("example.com:14005")).unwrap();
+        let networking = networking(&settings);
+        assert_eq!(networking.servers, vec!["example.com:14005"]);
+        assert_eq!(networking.default_server, 0);
+    }
+
+    #[test]
+    fn appends_new_server() {
+        let settings = with_default_server(SETTINGS, &server("127.0.0.1")).unwrap();
+        let networking = networking(&settings);
+        assert_eq!(
+            networking.servers,
+            vec!["server.veloren.net", "example.com:14005", "127.0.0.1:14004"]
+        );
+        assert_eq!(networking.default_server, 2);
+        assert_eq!(networking.username, "player");
+        assert!(settings.contains("crosshair_type: Round,"));
+        assert!(settings.contains("// Voxygen keeps unknown fields"));
+        assert!(settings.contains(r#"trusted_auth_servers: ["https://auth.veloren.net"],"#));
+    }
+
+    #[test]
+    fn selects_known_server() {
+        // The default port doesn't have to be written out
+        let settings = with_default_server(SETTINGS, &server("Server.veloren.net:14004")).unwrap();
+        assert_eq!(networking(&settings).default_server, 0);
+
+        let settings = with_default_server(SETTINGS, &server("example.com:14005")).unwrap();
+        let networking = networking(&settings);
+        assert_eq!(networking.servers.len(), 2);
+        assert_eq!(networking.default_server, 1);
+    }
+
+    #[test]
+    fn adds_missing_fields() {
+        let settings = with_default_server("(gameplay: ())", &server("example.com")).unwrap();
+        assert_eq!(networking(&settings).servers, vec!["example.com:14004"]);
+
+        let settings =
+            with_default_server("(networking: (username: \"a\"))", &server("example.com")).unwrap();
+        let networking = networking(&settings);
+        assert_eq!(networking.servers, vec!["example.com:14004"]);
+        assert_eq!(networking.default_server, 0);
+        assert_eq!(networking.username, "a");
+    }
+
+    #[test]
+    fn rejects_invalid_settings() {
+        assert!(with_default_server("(networking: (", &server("example.com")).is_none());
+        assert!(with_default_server("[1, 2]", &server("example.com")).is_none());
+        assert!(
+            with_default_server("(networking: (servers: [1]))", &server("example.com")).is_none()
+        );
+    }
+}
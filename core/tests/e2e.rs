@@ -0,0 +1,563 @@
+//! Runs update checks, downloads and installs against a local stand-in for download.veloren.net.
+//!
+//! The transport is global, hence the tests take turns via [`setup`].
+
+use airshipper_core::{
+    filesystem,
+    network::{self, FakeTransport, HttpTransport, NetworkSettings, Transport},
+    profiles::{Channel, Profile},
+    store, Result,
+};
+use futures::future::BoxFuture;
+use isahc::{
+    http::{Request, Response},
+    Body,
+};
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Cursor, Write},
+    net::{TcpListener, TcpStream},
+    sync::{Arc, Mutex, MutexGuard, Once},
+};
+
+/// How the stand-in answers a path
+#[derive(Clone)]
+enum Route {
+    Ok(Vec<u8>),
+    Redirect(String),
+    /// Answers with `503 Service Unavailable`
+    Unavailable,
+    /// Answers with `304 Not Modified` if the client knows the etag
+    Tagged {
+        body: Vec<u8>,
+        etag: String,
+    },
+    /// Answers `Range` requests with `206 Partial Content`
+    Ranged(Vec<u8>),
+    /// Announces the full length but closes the connection after sending `sent` bytes
+    Truncated {
+        body: Vec<u8>,
+        sent: usize,
+    },
+}
+
+/// Minimal HTTP server serving the routes, everything else is a `404 Not Found`.
+struct StandIn {
+    address: String,
+    routes: Arc<Mutex<HashMap<String, Route>>>,
+}
+
+impl StandIn {
+    fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = format!("http://{}", listener.local_addr().unwrap());
+        let routes = Arc::new(Mutex::new(HashMap::new()));
+
+        let served = routes.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let routes = served.clone();
+                std::thread::spawn(move || serve(stream.unwrap(), &routes));
+            }
+        });
+        Self { address, routes }
+    }
+
+    fn route(&self, path: &str, route: Route) {
+        self.routes.lock().unwrap().insert(path.to_owned(), route);
+    }
+}
+
+fn serve(mut stream: TcpStream, routes: &Mutex<HashMap<String, Route>>) {
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).unwrap();
+    let mut if_none_match = None;
+    let mut range = None;
+    let mut line = String::new();
+    while reader.read_line(&mut line).unwrap() > 2 {
+        let mut header = line.splitn(2, ':');
+        if let (Some(name), Some(value)) = (header.next(), header.next()) {
+            if name.eq_ignore_ascii_case("If-None-Match") {
+                if_none_match = Some(value.trim().to_owned());
+            } else if name.eq_ignore_ascii_case("Range") {
+                range = Some(value.trim().to_owned());
+            }
+        }
+        line.clear();
+    }
+
+    let path = request_line.split_whitespace().nth(1).unwrap_or("/");
+    let route = routes.lock().unwrap().get(path).cloned();
+    let (status, headers, body, sent) = match route {
+        Some(Route::Ok(body)) => {
+            let sent = body.len();
+            ("200 OK", String::new(), body, sent)
+        }
+        Some(Route::Redirect(location)) => (
+            "302 Found",
+            format!("Location: {}\r\n", location),
+            Vec::new(),
+            0,
+        ),
+        Some(Route::Tagged { etag, .. }) if if_none_match.as_ref() == Some(&etag) => (
+            "304 Not Modified",
+            format!("ETag: {}\r\n", etag),
+            Vec::new(),
+            0,
+        ),
+        Some(Route::Tagged { body, etag }) => {
+            let sent = body.len();
+            ("200 OK", format!("ETag: {}\r\n", etag), body, sent)
+        }
+        Some(Route::Ranged(body)) => match range.as_ref().and_then(|x| parse_range(x)) {
+            Some((start, end)) => (
+                "206 Partial Content",
+                format!(
+                    "Accept-Ranges: bytes\r\nContent-Range: bytes {}-{}/{}\r\n",
+                    start,
+                    end - 1,
+                    body.len()
+                ),
+                body[start..end].to_vec(),
+                end - start,
+            ),
+            None => {
+                let sent = body.len();
+                ("200 OK", "Accept-Ranges: bytes\r\n".into(), body, sent)
+            }
+        },
+        Some(Route::Truncated { body, sent }) => ("200 OK", String::new(), body, sent),
+        Some(Route::Unavailable) => ("503 Service Unavailable", String::new(), Vec::new(), 0),
+        None => ("404 Not Found", String::new(), b"Not Found".to_vec(), 9),
+    };
+
+    let _ = write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n{}\r\n",
+        status,
+        body.len(),
+        headers
+    );
+    let _ = stream.write_all(&body[..sent]);
+}
+
+/// Returns the start and end (exclusive) of e.g. `bytes=0-99`.
+fn parse_range(range: &str) -> Option<(usize, usize)> {
+    let mut bounds = range.trim_start_matches("bytes=").splitn(2, '-');
+    let start = bounds.next()?.parse().ok()?;
+    let end: usize = bounds.next()?.parse().ok()?;
+    Some((start, end + 1))
+}
+
+/// Sends the requests meant for the download server to the stand-in instead.
+struct Rebase {
+    base: String,
+    inner: HttpTransport,
+}
+
+impl Transport for Rebase {
+    fn send(&self, request: Request<()>) -> BoxFuture<'_, Result<Response<Body>>> {
+        let url = request
+            .uri()
+            .to_string()
+            .replace(network::DOWNLOAD_SERVER, &self.base);
+        let (mut parts, body) = request.into_parts();
+        parts.uri = url.parse().unwrap();
+        self.inner.send(Request::from_parts(parts, body))
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref STAND_IN: StandIn = StandIn::start();
+    static ref TURN: Mutex<()> = Mutex::new(());
+}
+
+/// Points airshipper to a temporary directory and the stand-in.
+/// Every scenario gets its own prefix, e.g. `/update_found/version/linux/Nightly`.
+fn setup(scenario: &str) -> MutexGuard<'static, ()> {
+    static ROOT: Once = Once::new();
+    ROOT.call_once(|| {
+        let root = std::env::temp_dir().join(format!("airshipper-e2e-{}", std::process::id()));
+        std::env::set_var(filesystem::ROOT_ENV, root);
+    });
+
+    // Another test failing must not fail all following ones
+    let turn = TURN.lock().unwrap_or_else(|e| e.into_inner());
+    network::configure(&NetworkSettings::default());
+    network::set_transport(Rebase {
+        base: format!("{}/{}", STAND_IN.address, scenario),
+        inner: HttpTransport::new(),
+    });
+    turn
+}
+
+fn version_path(scenario: &str) -> String {
+    format!("/{}/version/{}/Nightly", scenario, std::env::consts::OS)
+}
+
+fn artifact_path(scenario: &str) -> String {
+    format!("/{}/latest/{}/Nightly", scenario, std::env::consts::OS)
+}
+
+fn profile(name: &str, version: &str) -> Profile {
+    let mut profile = Profile::new(name.to_owned(), Channel::Nightly);
+    profile.version = version.to_owned();
+    profile
+}
+
+/// Creates an archive laid out like the nightly builds.
+fn build() -> Vec<u8> {
+    let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let options = zip::write::FileOptions::default();
+    // The first entry gets skipped by the installer
+    zip.add_directory("assets/", options).unwrap();
+    zip.start_file(filesystem::VOXYGEN_FILE, options).unwrap();
+    zip.write_all(b"#!/bin/sh\n").unwrap();
+    zip.start_file("assets/voxygen.txt", options).unwrap();
+    zip.write_all(&[42; 64 * 1024]).unwrap();
+    zip.finish().unwrap().into_inner()
+}
+
+/// Downloads and installs the latest build.
+async fn update(profile: &Profile) -> Result<Profile> {
+    let (_, download) = network::download(profile, None, Default::default()).await?;
+    download.await?;
+    profile.clone().install().await
+}
+
+#[async_std::test]
+async fn update_found() {
+    let _turn = setup("update_found");
+    STAND_IN.route(&version_path("update_found"), Route::Ok(b"v2".to_vec()));
+    STAND_IN.route(&artifact_path("update_found"), Route::Ok(build()));
+
+    let profile = profile("update_found", "v1");
+    assert_eq!(profile.check_for_update().await.unwrap(), "v2");
+
+    let profile = update(&profile).await.unwrap();
+    assert_eq!(profile.version, "v2");
+    assert!(profile.is_installed());
+    assert!(profile.directory.join("assets/voxygen.txt").exists());
+    assert!(!filesystem::get_download_path(&profile.name).exists());
+}
+
+#[async_std::test]
+async fn release_after_download() {
+    let _turn = setup("release_after_download");
+    STAND_IN.route(
+        &version_path("release_after_download"),
+        Route::Ok(b"v2".to_vec()),
+    );
+    STAND_IN.route(&artifact_path("release_after_download"), Route::Ok(build()));
+
+    let profile = profile("release_after_download", "v1");
+    let (_, download) = network::download(&profile, None, Default::default())
+        .await
+        .unwrap();
+    download.await.unwrap();
+    assert_eq!(profile.staged_version().as_deref(), Some("v2"));
+
+    // The downloaded build is what gets installed, not the latest one
+    STAND_IN.route(
+        &version_path("release_after_download"),
+        Route::Ok(b"v3".to_vec()),
+    );
+    let profile = profile.install().await.unwrap();
+    assert_eq!(profile.version, "v2");
+}
+
+#[async_std::test]
+async fn up_to_date() {
+    let _turn = setup("up_to_date");
+    STAND_IN.route(&version_path("up_to_date"), Route::Ok(b"v1".to_vec()));
+
+    let profile = profile("up_to_date", "v1");
+    assert_eq!(profile.check_for_update().await.unwrap(), profile.version);
+    // Nothing to install
+    assert!(profile.install().await.is_err());
+}
+
+#[async_std::test]
+async fn not_found() {
+    let _turn = setup("not_found");
+
+    let profile = profile("not_found", "v1");
+    assert!(profile.check_for_update().await.is_err());
+
+    let error = network::download(&profile, None, Default::default())
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("404"), "{}", error);
+    assert!(!filesystem::get_download_path(&profile.name).exists());
+}
+
+#[async_std::test]
+async fn corrupt_zip() {
+    let _turn = setup("corrupt_zip");
+    STAND_IN.route(&version_path("corrupt_zip"), Route::Ok(b"v2".to_vec()));
+    STAND_IN.route(
+        &artifact_path("corrupt_zip"),
+        Route::Ok(b"this is not a zip archive".to_vec()),
+    );
+
+    let profile = profile("corrupt_zip", "v1");
+    let error = update(&profile).await.unwrap_err();
+    assert_eq!(error.exit_code(), 5, "{}", error);
+    assert!(!profile.is_installed());
+}
+
+#[async_std::test]
+async fn truncated_download() {
+    let _turn = setup("truncated_download");
+    STAND_IN.route(
+        &version_path("truncated_download"),
+        Route::Ok(b"v2".to_vec()),
+    );
+    STAND_IN.route(
+        &artifact_path("truncated_download"),
+        Route::Truncated {
+            body: build(),
+            sent: 1000,
+        },
+    );
+
+    let profile = profile("truncated_download", "v1");
+    let (progress, download) = network::download(&profile, None, Default::default())
+        .await
+        .unwrap();
+    assert!(download.await.is_err());
+    assert!(matches!(progress.finished(), Some(Err(_))));
+    // Partial downloads must never be installed
+    assert!(!filesystem::get_download_path(&profile.name).exists());
+    assert!(profile.install().await.is_err());
+}
+
+#[async_std::test]
+async fn redirect_chain() {
+    let _turn = setup("redirect_chain");
+    STAND_IN.route(
+        &version_path("redirect_chain"),
+        Route::Redirect("/redirect_chain/version/1".into()),
+    );
+    STAND_IN.route(
+        "/redirect_chain/version/1",
+        Route::Redirect(format!("{}/redirect_chain/version/2", STAND_IN.address)),
+    );
+    STAND_IN.route("/redirect_chain/version/2", Route::Ok(b"v3".to_vec()));
+    STAND_IN.route(
+        &artifact_path("redirect_chain"),
+        Route::Redirect("/redirect_chain/builds/1".into()),
+    );
+    STAND_IN.route(
+        "/redirect_chain/builds/1",
+        Route::Redirect("/redirect_chain/builds/2".into()),
+    );
+    STAND_IN.route("/redirect_chain/builds/2", Route::Ok(build()));
+
+    let profile = profile("redirect_chain", "v1");
+    assert_eq!(profile.check_for_update().await.unwrap(), "v3");
+    assert_eq!(update(&profile).await.unwrap().version, "v3");
+}
+
+#[async_std::test]
+async fn redirect_loop() {
+    let _turn = setup("redirect_loop");
+    STAND_IN.route(
+        &version_path("redirect_loop"),
+        Route::Redirect(version_path("redirect_loop")),
+    );
+
+    let profile = profile("redirect_loop", "v1");
+    assert!(profile.check_for_update().await.is_err());
+}
+
+#[async_std::test]
+async fn mirror_failover() {
+    let _turn = setup("mirror_failover");
+    let broken = format!("{}/mirror_failover/broken", STAND_IN.address);
+    let healthy = format!("{}/mirror_failover/healthy", STAND_IN.address);
+    let mut settings = NetworkSettings::default();
+    settings.retries = 1;
+    settings
+        .mirrors
+        .insert(Channel::Nightly, vec![broken.clone(), healthy.clone()]);
+    network::configure(&settings);
+
+    for mirror in &["broken", "healthy"] {
+        let scenario = format!("mirror_failover/{}", mirror);
+        let route = |ok| {
+            if *mirror == "healthy" {
+                ok
+            } else {
+                Route::Unavailable
+            }
+        };
+        STAND_IN.route(&version_path(&scenario), route(Route::Ok(b"v2".to_vec())));
+        STAND_IN.route(&artifact_path(&scenario), route(Route::Ok(build())));
+    }
+
+    let profile = profile("mirror_failover", "v1");
+    assert_eq!(profile.check_for_update().await.unwrap(), "v2");
+    // The healthy mirror gets asked first from now on
+    assert_eq!(network::mirrors(Channel::Nightly)[0], healthy);
+    // Also after restarting airshipper
+    let remembered = std::fs::read_to_string(filesystem::get_mirrors_path()).unwrap();
+    assert!(remembered.contains(&healthy));
+    assert_eq!(update(&profile).await.unwrap().version, "v2");
+}
+
+#[async_std::test]
+async fn segmented_download() {
+    let _turn = setup("segmented_download");
+    // Big enough for several segments, stored as is
+    let contents = (0..3 * 1024 * 1024u32)
+        .map(|x| (x.wrapping_mul(2_654_435_761) >> 24) as u8)
+        .collect::<Vec<_>>();
+    let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let options =
+        zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    zip.add_directory("assets/", options).unwrap();
+    zip.start_file(filesystem::VOXYGEN_FILE, options).unwrap();
+    zip.write_all(b"#!/bin/sh\n").unwrap();
+    zip.start_file("assets/voxygen.bin", options).unwrap();
+    zip.write_all(&contents).unwrap();
+    let build = zip.finish().unwrap().into_inner();
+
+    STAND_IN.route(
+        &version_path("segmented_download"),
+        Route::Ok(b"v2".to_vec()),
+    );
+    STAND_IN.route(&artifact_path("segmented_download"), Route::Ranged(build));
+
+    let profile = update(&profile("segmented_download", "v1")).await.unwrap();
+    assert_eq!(profile.version, "v2");
+    let installed = std::fs::read(profile.directory.join("assets/voxygen.bin")).unwrap();
+    assert!(installed == contents);
+}
+
+#[async_std::test]
+async fn streaming_install() {
+    let _turn = setup("streaming_install");
+    let mut tar = tar::Builder::new(Vec::new());
+    for (path, contents) in &[
+        (filesystem::VOXYGEN_FILE, &b"#!/bin/sh\n"[..]),
+        ("assets/voxygen.txt", &[42; 64 * 1024][..]),
+    ] {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o755);
+        header.set_cksum();
+        tar.append_data(&mut header, path, *contents).unwrap();
+    }
+    STAND_IN.route(
+        &version_path("streaming_install"),
+        Route::Ok(b"v2".to_vec()),
+    );
+    STAND_IN.route(
+        &artifact_path("streaming_install"),
+        Route::Redirect("/streaming_install/builds/veloren.tar".into()),
+    );
+    STAND_IN.route(
+        "/streaming_install/builds/veloren.tar",
+        Route::Ok(tar.into_inner().unwrap()),
+    );
+
+    let profile = profile("streaming_install", "v1");
+    let (_, download) = network::download(&profile, None, Default::default())
+        .await
+        .unwrap();
+    download.await.unwrap();
+    // Extracted right away, without keeping the archive around
+    assert!(filesystem::get_extract_path(&profile.name).is_dir());
+    assert!(!filesystem::get_download_path(&profile.name).exists());
+
+    let profile = profile.install().await.unwrap();
+    assert_eq!(profile.version, "v2");
+    assert!(profile.directory.join("assets/voxygen.txt").exists());
+    assert!(!filesystem::get_extract_path(&profile.name).exists());
+}
+
+#[async_std::test]
+async fn shared_store() {
+    let _turn = setup("shared_store");
+    let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let options = zip::write::FileOptions::default();
+    zip.add_directory("assets/", options).unwrap();
+    zip.start_file(filesystem::VOXYGEN_FILE, options).unwrap();
+    zip.write_all(b"#!/bin/sh\n").unwrap();
+    // Not part of any other build, hence only these profiles use it
+    zip.start_file("assets/shared_store.txt", options).unwrap();
+    zip.write_all(&[7; 128 * 1024]).unwrap();
+    STAND_IN.route(&version_path("shared_store"), Route::Ok(b"v2".to_vec()));
+    STAND_IN.route(
+        &artifact_path("shared_store"),
+        Route::Ok(zip.finish().unwrap().into_inner()),
+    );
+
+    let mut profiles = Vec::new();
+    for name in &["shared_store_a", "shared_store_b"] {
+        profiles.push(update(&profile(name, "v1")).await.unwrap());
+    }
+    let file = |profile: &Profile| profile.directory.join("assets/shared_store.txt");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        // Store and both profiles
+        let links = std::fs::metadata(file(&profiles[0])).unwrap().nlink();
+        assert_eq!(links, 3);
+    }
+
+    let usage = profiles[0].disk_usage();
+    assert!(usage.shared >= 128 * 1024);
+    assert_eq!(usage.staged, 0);
+
+    // Still used by the other profile
+    std::fs::remove_dir_all(&profiles[1].directory).unwrap();
+    store::forget(&profiles[1].name).unwrap();
+    store::collect_garbage().unwrap();
+    assert_eq!(
+        std::fs::read(file(&profiles[0])).unwrap(),
+        vec![7; 128 * 1024]
+    );
+
+    std::fs::remove_dir_all(&profiles[0].directory).unwrap();
+    store::forget(&profiles[0].name).unwrap();
+    assert!(store::collect_garbage().unwrap() >= 128 * 1024);
+}
+
+#[async_std::test]
+async fn conditional_fetch() {
+    let _turn = setup("conditional_fetch");
+    let url = format!("{}/conditional_fetch/news", STAND_IN.address);
+    let tagged = |body: &str| Route::Tagged {
+        body: body.into(),
+        etag: "\"1\"".into(),
+    };
+
+    STAND_IN.route("/conditional_fetch/news", tagged("first"));
+    assert_eq!(network::fetch(&url).await.unwrap(), "first");
+    // Same etag, hence the stored copy gets used
+    STAND_IN.route("/conditional_fetch/news", tagged("second"));
+    assert_eq!(network::fetch(&url).await.unwrap(), "first");
+
+    STAND_IN.route("/conditional_fetch/news", Route::Ok(b"third".to_vec()));
+    assert_eq!(network::fetch(&url).await.unwrap(), "third");
+}
+
+#[async_std::test]
+async fn fake_transport() {
+    let _turn = setup("fake_transport");
+    let fake = FakeTransport::new();
+    let url = format!(
+        "{}/version/{}/Nightly",
+        network::DOWNLOAD_SERVER,
+        std::env::consts::OS
+    );
+    fake.respond(&url, 200, "v4");
+    network::set_transport(fake.clone());
+
+    let profile = profile("fake_transport", "v1");
+    assert_eq!(profile.check_for_update().await.unwrap(), "v4");
+    assert_eq!(fake.requests(), vec![url]);
+}